@@ -0,0 +1,279 @@
+use bls12_381::Scalar;
+use color_eyre::{eyre::ensure, Result};
+use rand_core::{OsRng, RngCore};
+use zeroize::Zeroize;
+
+use super::secret::Secret;
+use sha2::{Digest, Sha512};
+use std::{
+    fmt, mem,
+    path::{Path, PathBuf},
+    process::Command,
+    slice,
+    time::Instant,
+};
+
+const POOL_DST: &[u8] = b"MAJLIS_KZG_ENTROPY_POOL_V1";
+const SECRET_DST: &[u8] = b"MAJLIS_KZG_SECRET_V1";
+
+/// Number of timing samples mixed into the pool.
+const JITTER_SAMPLES: usize = 1024;
+
+/// Where a piece of entropy came from. Only the kind of source is ever reported, never its value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EntropySource {
+    Os,
+    TimingJitter,
+    UserInput,
+    File(PathBuf),
+    Command(String),
+}
+
+impl fmt::Display for EntropySource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Os => write!(f, "OS randomness"),
+            Self::TimingJitter => write!(f, "timing jitter"),
+            Self::UserInput => write!(f, "user input"),
+            Self::File(path) => write!(f, "file `{}`", path.display()),
+            Self::Command(cmd) => write!(f, "output of `{cmd}`"),
+        }
+    }
+}
+
+/// Entropy pool that the secrets of a contribution are derived from.
+///
+/// Every source is hashed into the pool together with a tag and its length, so sources can't be
+/// shifted into one another. OS randomness and timing jitter are always part of the pool, which
+/// is kept in guarded memory.
+pub struct Entropy {
+    pool: Secret<[u8; 64]>,
+    sources: Vec<EntropySource>,
+}
+
+impl Entropy {
+    pub fn new() -> Self {
+        let mut pool = Secret::new([0u8; 64]);
+        Hasher::default()
+            .update(POOL_DST)
+            .finalize_into(pool.expose_mut());
+
+        let mut entropy = Self {
+            pool,
+            sources: Vec::new(),
+        };
+
+        let mut os = Secret::new([0u8; 64]);
+        OsRng.fill_bytes(os.expose_mut());
+        entropy.mix(EntropySource::Os, os.expose());
+
+        entropy.mix(EntropySource::TimingJitter, &timing_jitter());
+        entropy
+    }
+
+    /// Mixes in text typed by the user. Empty input is ignored.
+    pub fn add_user_input<T: AsRef<str>>(&mut self, input: T) {
+        let input = input.as_ref();
+        if !input.is_empty() {
+            self.mix(EntropySource::UserInput, input.as_bytes());
+        }
+    }
+
+    pub fn add_file<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        let mut bytes = std::fs::read(path)?;
+        ensure!(
+            !bytes.is_empty(),
+            "entropy file `{}` is empty",
+            path.display()
+        );
+
+        self.mix(EntropySource::File(path.to_path_buf()), &bytes);
+        bytes.zeroize();
+        Ok(())
+    }
+
+    /// Runs `cmd` through the shell and mixes in whatever it writes to stdout.
+    pub fn add_command<T: AsRef<str>>(&mut self, cmd: T) -> Result<()> {
+        let cmd = cmd.as_ref();
+        let mut output = Command::new("sh").arg("-c").arg(cmd).output()?;
+
+        ensure!(output.status.success(), "entropy command `{cmd}` failed");
+        ensure!(
+            !output.stdout.is_empty(),
+            "entropy command `{cmd}` produced no output"
+        );
+
+        self.mix(EntropySource::Command(cmd.to_string()), &output.stdout);
+        output.stdout.zeroize();
+        Ok(())
+    }
+
+    pub fn sources(&self) -> &[EntropySource] {
+        &self.sources
+    }
+
+    /// Derives `n` distinct secrets from the pool, consuming it.
+    ///
+    /// Each secret is a 64-byte hash reduced modulo the group order, which keeps the bias of the
+    /// reduction negligible. Secrets without entropy (`0` or `1`) are skipped.
+    pub fn into_secrets(self, n: usize) -> Vec<Secret<Scalar>> {
+        let mut secrets: Vec<Secret<Scalar>> = Vec::with_capacity(n);
+        let mut wide = Secret::new([0u8; 64]);
+
+        let mut counter = 0u64;
+        while secrets.len() < n {
+            Hasher::default()
+                .update(SECRET_DST)
+                .update(self.pool.expose())
+                .update(counter.to_be_bytes())
+                .finalize_into(wide.expose_mut());
+            counter += 1;
+
+            let mut x = Secret::new(Scalar::zero());
+            *x.expose_mut() = Scalar::from_bytes_wide(wide.expose());
+            let x_val = x.expose();
+
+            if *x_val != Scalar::zero()
+                && *x_val != Scalar::one()
+                && !secrets.iter().any(|s| s.expose() == x_val)
+            {
+                secrets.push(x);
+            }
+        }
+
+        secrets
+    }
+
+    fn mix(&mut self, source: EntropySource, bytes: &[u8]) {
+        let tag = match source {
+            EntropySource::Os => 0u8,
+            EntropySource::TimingJitter => 1,
+            EntropySource::UserInput => 2,
+            EntropySource::File(_) => 3,
+            EntropySource::Command(_) => 4,
+        };
+
+        let mut hasher = Hasher::default();
+        hasher
+            .update(self.pool.expose())
+            .update([tag])
+            .update((bytes.len() as u64).to_be_bytes())
+            .update(bytes);
+        hasher.finalize_into(self.pool.expose_mut());
+        self.sources.push(source);
+    }
+}
+
+/// SHA-512 that wipes its state on drop. Until it's finalized it holds the end of its input, and
+/// after that a state the output can be recomputed from.
+#[derive(Default)]
+struct Hasher(Sha512);
+
+impl Hasher {
+    fn update<T: AsRef<[u8]>>(&mut self, data: T) -> &mut Self {
+        Digest::update(&mut self.0, data);
+        self
+    }
+
+    fn finalize_into(&mut self, out: &mut [u8; 64]) {
+        Digest::finalize_into_reset(&mut self.0, out.into());
+    }
+}
+
+impl Drop for Hasher {
+    fn drop(&mut self) {
+        // SAFETY: `Sha512` is plain data without drop glue, and all zeroes is a valid value of it.
+        unsafe {
+            slice::from_raw_parts_mut(
+                &mut self.0 as *mut Sha512 as *mut u8,
+                mem::size_of::<Sha512>(),
+            )
+        }
+        .zeroize();
+    }
+}
+
+impl Default for Entropy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Samples how long a small amount of work takes. The exact durations depend on scheduling,
+/// caches and frequency scaling, which adds some unpredictability independent of the OS RNG.
+fn timing_jitter() -> Vec<u8> {
+    let mut samples = Vec::with_capacity(JITTER_SAMPLES * 4);
+    let mut acc = 0u64;
+
+    for i in 0..JITTER_SAMPLES {
+        let start = Instant::now();
+        for j in 0..(64 + i % 64) {
+            acc = acc.wrapping_mul(6364136223846793005).wrapping_add(j as u64);
+        }
+        samples.extend_from_slice(&start.elapsed().subsec_nanos().to_le_bytes());
+    }
+
+    samples.extend_from_slice(&acc.to_le_bytes());
+    samples
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn derive_distinct_secrets() {
+        let secrets = Entropy::new().into_secrets(4);
+
+        assert_eq!(secrets.len(), 4);
+        for (i, x) in secrets.iter().enumerate() {
+            assert!(!secrets[i + 1..].iter().any(|y| y.expose() == x.expose()));
+        }
+    }
+
+    #[test]
+    fn fresh_pools_derive_different_secrets() {
+        let mut a = Entropy::new();
+        let mut b = Entropy::new();
+        a.add_user_input("same input");
+        b.add_user_input("same input");
+
+        assert_ne!(a.into_secrets(1)[0].expose(), b.into_secrets(1)[0].expose());
+    }
+
+    #[test]
+    fn hasher_is_wiped_on_drop() {
+        let mut hasher = mem::ManuallyDrop::new(Hasher::default());
+        hasher.update([0xff; 100]);
+
+        // SAFETY: the hasher isn't used after it's dropped, only its memory is read
+        unsafe { mem::ManuallyDrop::drop(&mut hasher) };
+        let bytes = unsafe {
+            slice::from_raw_parts(
+                &*hasher as *const Hasher as *const u8,
+                mem::size_of::<Hasher>(),
+            )
+        };
+        assert!(bytes.iter().all(|byte| *byte == 0));
+    }
+
+    #[test]
+    fn sources_do_not_reveal_values() {
+        let mut entropy = Entropy::new();
+        entropy.add_user_input("hunter2");
+        entropy.add_command("echo entropy").unwrap();
+
+        let report = entropy
+            .sources()
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        assert_eq!(
+            report,
+            "OS randomness, timing jitter, user input, output of `echo entropy`"
+        );
+    }
+}
@@ -1,6 +1,11 @@
-use color_eyre::{eyre::ensure, Result};
+use color_eyre::{
+    eyre::{bail, ensure},
+    Result,
+};
 use serde_json::Value;
 
+const GITHUB_API_URL: &str = "https://api.github.com/";
+
 pub fn eth_address_to_identity<T: AsRef<str>>(eth_address: T) -> Result<String> {
     let addr = eth_address.as_ref();
     let has_prefix = addr.strip_prefix("0x");
@@ -10,31 +15,39 @@ pub fn eth_address_to_identity<T: AsRef<str>>(eth_address: T) -> Result<String>
         "Ethereum Addresses must start with `0x`."
     );
 
-    hex::decode(&has_prefix.unwrap())?;
-    return Ok(format!("eth|{addr}"));
+    hex::decode(has_prefix.unwrap())?;
+    Ok(format!("eth|{addr}"))
 }
 
 // this function assumes that the `@` in the handle is already removed
-async fn get_github_id<T: AsRef<str>>(github_handle: T) -> Result<u64> {
+async fn get_github_id<T: AsRef<str>>(api_url: &str, github_handle: T) -> Result<u64> {
     let handle = github_handle.as_ref();
 
     // GitHub API requires request to have valid `User-Agent` header `https://docs.github.com/en/rest/overview/resources-in-the-rest-api?apiVersion=2022-11-28#user-agent-required`
     let client = reqwest::ClientBuilder::new().user_agent(handle).build()?;
 
     let res = client
-        .get(format!("https://api.github.com/users/{handle}"))
+        .get(format!("{api_url}users/{handle}"))
         .send()
         .await?
         .error_for_status()?
         .json::<Value>()
         .await?;
 
-    return res["id"]
+    res["id"]
         .as_u64()
-        .ok_or(color_eyre::eyre::eyre!("field `id` doesn't exist"));
+        .ok_or(color_eyre::eyre::eyre!("field `id` doesn't exist"))
 }
 
 pub async fn github_handle_to_identity<T: AsRef<str>>(github_handle: T) -> Result<String> {
+    github_handle_to_identity_at(GITHUB_API_URL, github_handle).await
+}
+
+/// Same as [`github_handle_to_identity`], looking the user up on the GitHub API at `api_url`.
+async fn github_handle_to_identity_at<T: AsRef<str>>(
+    api_url: &str,
+    github_handle: T,
+) -> Result<String> {
     let handle = github_handle.as_ref();
     let without_prefix = handle.strip_prefix('@');
 
@@ -43,13 +56,43 @@ pub async fn github_handle_to_identity<T: AsRef<str>>(github_handle: T) -> Resul
         "GitHub handles must start with `@`."
     );
 
-    let id = get_github_id(without_prefix.unwrap()).await?;
-    return Ok(format!("git|{id}|{}", handle.to_lowercase()));
+    let id = get_github_id(api_url, without_prefix.unwrap()).await?;
+    github_id_to_identity(id, handle)
+}
+
+/// Builds the identity of a GitHub user whose numeric ID is already known.
+pub fn github_id_to_identity<T: AsRef<str>>(id: u64, github_handle: T) -> Result<String> {
+    let handle = github_handle.as_ref();
+
+    ensure!(
+        handle.starts_with('@'),
+        "GitHub handles must start with `@`."
+    );
+
+    Ok(format!("git|{id}|{}", handle.to_lowercase()))
+}
+
+/// Resolves a user-supplied Ethereum address (`0x…`) or GitHub handle (`@…`) into the identity
+/// string the sequencer expects the BLS signatures to be made over.
+pub async fn resolve_identity<T: AsRef<str>>(identity: T) -> Result<String> {
+    let identity = identity.as_ref();
+
+    if identity.starts_with("0x") {
+        eth_address_to_identity(identity)
+    } else if identity.starts_with('@') {
+        github_handle_to_identity(identity).await
+    } else {
+        bail!("unknown identity format")
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::server::http::tests::TestServer;
+    use axum::{
+        extract::Path, http::StatusCode, response::IntoResponse, routing::get, Json, Router,
+    };
 
     #[test]
     fn create_identity_from_eth_address() {
@@ -58,11 +101,41 @@ mod tests {
         assert_eq!(iden, format!("eth|{addr}"))
     }
 
-    #[tokio::test]
+    #[tokio::test(flavor = "multi_thread")]
     async fn create_identity_from_github_handle() {
+        // the users endpoint of the GitHub API
+        let github = TestServer::spawn(|_| {
+            Router::new().route(
+                "/users/:handle",
+                get(|Path(handle): Path<String>| async move {
+                    match handle.as_str() {
+                        "kariy" => Json(serde_json::json!({ "id": 26515232 })).into_response(),
+                        _ => StatusCode::NOT_FOUND.into_response(),
+                    }
+                }),
+            )
+        });
         let handle = "@kariy";
-        let iden = github_handle_to_identity(handle).await.unwrap();
-        assert_eq!(iden, format!("git|26515232|{handle}"))
+        let iden = github_handle_to_identity_at(github.url.as_str(), handle)
+            .await
+            .unwrap();
+        assert_eq!(iden, format!("git|26515232|{handle}"));
+
+        assert!(github_handle_to_identity_at(github.url.as_str(), "@nobody")
+            .await
+            .is_err());
+    }
+
+    #[test]
+    fn create_identity_from_github_id() {
+        let iden = github_id_to_identity(26515232, "@Kariy").unwrap();
+        assert_eq!(iden, "git|26515232|@kariy");
+        assert!(github_id_to_identity(26515232, "kariy").is_err());
+    }
+
+    #[tokio::test]
+    async fn resolve_unknown_identity_format() {
+        assert!(resolve_identity("kariy").await.is_err())
     }
 
     #[tokio::test]
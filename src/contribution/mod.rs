@@ -1,126 +1,211 @@
 use bls12_381::{G1Affine, G2Affine, Scalar};
-use rand_chacha::ChaCha8Rng;
-use rand_core::{RngCore, SeedableRng};
 use rayon::prelude::*;
 
+pub mod entropy;
 pub mod identity;
+pub mod point;
+pub mod secret;
 pub mod signing;
+pub mod subgroup;
 pub mod types;
 pub mod utils;
-
+pub mod validation;
+pub mod verification;
+
+use entropy::Entropy;
+use point::{G1Point, G2Point};
+use secret::Secret;
+use signing::sign_identity;
+use subgroup::{batch_subgroup_check, Group, SubgroupError};
 use types::{BatchContribution, Contribution};
-use utils::{bytes_from_hex_str, g1_point_from_compressed, g2_point_from_compressed};
-
-pub fn generate_random_scalar() -> Scalar {
-    let mut value = [0u8; 32];
-    let mut rng = ChaCha8Rng::from_entropy();
-    rng.fill_bytes(&mut value);
-    Scalar::from_raw(unsafe { std::mem::transmute::<[u8; 32], [u64; 4]>(value) })
-}
+use zeroize::Zeroize;
 
 /// Subgroup checks :
 /// - G1 Powers Subgroup check - For each of the Powers of Tau (g1_powers), verify that they are actually elements of the prime-ordered subgroup.
 /// - G2 Powers Subgroup check - For each of the Powers of Tau (g2_powers), verify that they are actually elements of the prime-ordered subgroup.
 /// - Running Product Subgroup check - Check that the last running product (the one the participant will interact with) is an element of the prime-ordered subgroup.
-pub fn subgroup_checks(batch_contribution: BatchContribution) -> bool {
-    let has_invalid = batch_contribution
-        .contributions
-        .iter()
-        .par_bridge()
-        .map(move |contr| {
-            for g1_power in contr.powers_of_tau.g1_powers.iter() {
-                let compressed = bytes_from_hex_str(g1_power);
-                return !g1_point_from_compressed(&compressed).is_none();
-            }
-
-            for g2_power in contr.powers_of_tau.g2_powers.iter() {
-                let compressed = bytes_from_hex_str(g2_power);
-                return !g2_point_from_compressed(&compressed).is_none();
-            }
-
-            true
-        })
-        .any(|res| res == false);
-
-    !has_invalid
+///   The running product we build on is the first power of tau in G1, so it is covered by the G1 powers check.
+pub fn subgroup_checks(batch_contribution: &BatchContribution) -> Result<(), SubgroupError> {
+    for (ceremony, contr) in batch_contribution.contributions.iter().enumerate() {
+        batch_subgroup_check(&contr.powers_of_tau.g1_powers).map_err(|index| SubgroupError {
+            ceremony,
+            group: Group::G1,
+            index,
+        })?;
+
+        batch_subgroup_check(&contr.powers_of_tau.g2_powers).map_err(|index| SubgroupError {
+            ceremony,
+            group: Group::G2,
+            index,
+        })?;
+    }
+
+    Ok(())
 }
 
 // Perform the ceremony
-pub fn update_batch(batch_contribution: &mut BatchContribution) {
-    let mut contributions = batch_contribution.contributions.clone();
-
-    contributions = contributions
-        .into_iter()
-        .par_bridge()
-        .map(move |contr| {
-            let x = generate_random_scalar();
-            update_powers_of_tau(contr, x)
-        })
-        .collect::<Vec<Contribution>>();
-
-    batch_contribution.contributions = contributions;
+///  `identity` is the resolved identity (eg. `git|1234|@handle`) that the BLS signatures are made over.
+///  Each sub-ceremony gets its own secret derived from `entropy`, which never leaves this function.
+pub fn update_batch<T: AsRef<str>>(
+    batch_contribution: &mut BatchContribution,
+    identity: T,
+    entropy: Entropy,
+) {
+    let mut secrets = entropy.into_secrets(batch_contribution.contributions.len());
+    apply_secrets(batch_contribution, identity.as_ref(), &mut secrets);
+}
+
+/// Applies the i-th secret to the i-th sub-ceremony, and wipes each secret as soon as it is used.
+fn apply_secrets(
+    batch_contribution: &mut BatchContribution,
+    identity: &str,
+    secrets: &mut [Secret<Scalar>],
+) {
+    batch_contribution
+        .contributions
+        .par_iter_mut()
+        .zip(secrets.par_iter_mut())
+        .for_each(|(contr, x)| {
+            update_powers_of_tau(contr, x);
+            update_witness(contr, x);
+
+            let signature = sign_identity(x, identity);
+            contr.bls_signature = Some(format!("0x{}", hex::encode(signature.to_bytes())));
+
+            x.expose_mut().zeroize();
+        });
 }
 
-// TODO: for each contribution, the `x` scalar must be different
 ///  Updates the Powers of Tau within a sub-ceremony by multiplying each with a successive power of the secret x.
-fn update_powers_of_tau(mut contribution: Contribution, x: Scalar) -> Contribution {
-    let mut x_i: Scalar = Scalar::one();
-    for i in 0..(contribution.num_g1_powers as usize) {
-        let power1 = contribution.powers_of_tau.g1_powers[i].as_str();
-        let point = g1_point_from_compressed(&bytes_from_hex_str(power1)).unwrap();
+fn update_powers_of_tau(contribution: &mut Contribution, x: &Secret<Scalar>) {
+    let powers = &mut contribution.powers_of_tau;
+    let mut g2_powers = powers.g2_powers.iter_mut();
 
-        let new_point = G1Affine::from(point * x_i);
-        contribution.powers_of_tau.g1_powers[i] =
-            format!("0x{}", hex::encode(new_point.to_compressed()));
+    let mut x_i = Secret::new(Scalar::one());
+    for power1 in powers.g1_powers.iter_mut() {
+        *power1 = G1Point(G1Affine::from(power1.0 * x_i.expose()));
 
         // update g2 powers
-        if i < contribution.num_g2_powers as usize {
-            let power2 = contribution.powers_of_tau.g2_powers[i].as_str();
-            let point = g2_point_from_compressed(&bytes_from_hex_str(power2)).unwrap();
-
-            let new_point = G2Affine::from(point * x_i);
-            contribution.powers_of_tau.g2_powers[i] =
-                format!("0x{}", hex::encode(new_point.to_compressed()));
+        if let Some(power2) = g2_powers.next() {
+            *power2 = G2Point(G2Affine::from(power2.0 * x_i.expose()));
         }
 
-        x_i = x_i * x;
+        *x_i.expose_mut() *= x.expose();
     }
-    contribution
 }
 
-pub fn update_witness(contribution: &mut Contribution, x: Scalar) {
-    let new_pot_pubkey = G2Affine::from(G2Affine::generator() * x);
-    contribution.pot_pubkey = format!("0x{}", hex::encode(new_pot_pubkey.to_compressed()));
+/// Sets the sub-ceremony's `pot_pubkey` to `[x]G2`, which the sequencer pairs against the
+/// updated powers to check that they were raised to the same secret.
+fn update_witness(contribution: &mut Contribution, x: &Secret<Scalar>) {
+    contribution.pot_pubkey = G2Point(G2Affine::from(G2Affine::generator() * x.expose()));
 }
 
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
     use super::*;
+    use crate::transcript::{current_contribution, genesis};
+    use bls12_381::pairing;
+    use blsful::{PublicKey, Signature};
     use std::fs;
+    use types::BatchTranscript;
+    use utils::bytes_from_hex_str;
+
+    pub(crate) fn genesis_batch(sizes: &[(i32, i32)]) -> BatchContribution {
+        current_contribution(&genesis(sizes))
+    }
+
+    #[test]
+    fn update_batch_is_consistent_with_secrets() {
+        let identity = "eth|0x73F8A075b9a1e3ddD169CfdBdFA513c40B8bd796";
+        let mut batch = genesis_batch(&[(8, 3), (16, 3)]);
+        update_batch(&mut batch, identity, Entropy::new());
+
+        let pubkeys = batch
+            .contributions
+            .iter()
+            .map(|contr| contr.pot_pubkey)
+            .collect::<Vec<_>>();
+        assert_ne!(
+            pubkeys[0], pubkeys[1],
+            "sub-ceremonies must use different secrets"
+        );
+
+        for contr in batch.contributions.iter() {
+            let tau_g1 = contr.powers_of_tau.g1_powers[1].0;
+            let pubkey = contr.pot_pubkey.0;
+
+            // the powers started from the generator, so `[x]G1` must pair with `[x]G2`
+            assert_eq!(
+                pairing(&tau_g1, &G2Affine::generator()),
+                pairing(&G1Affine::generator(), &pubkey)
+            );
+
+            let signature = contr.bls_signature.as_deref().unwrap();
+            let signature: [u8; 48] = bytes_from_hex_str(signature).unwrap().try_into().unwrap();
+            let signature = Signature::from_bytes(&signature).unwrap();
+            let pubkey = PublicKey::from_bytes(&pubkey.to_compressed()).unwrap();
+            assert!(bool::from(signature.verify(pubkey, identity)));
+        }
+    }
+
+    #[test]
+    fn secrets_are_wiped_after_contribution() {
+        // a pool of our own, so the secrets of other tests aren't counted
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(2)
+            .build()
+            .unwrap();
+        let live = || {
+            pool.broadcast(|_| secret::tests::LIVE.with(|live| live.get()))
+                .into_iter()
+                .sum::<isize>()
+        };
+        let dirty = || {
+            pool.broadcast(|_| secret::tests::DIRTY.with(|dirty| dirty.get()))
+                .into_iter()
+                .sum::<usize>()
+        };
+
+        let entropy = pool.install(Entropy::new);
+        assert!(live() > 0);
+
+        let mut batch = genesis_batch(&[(8, 3), (16, 3)]);
+        pool.install(|| update_batch(&mut batch, "eth|0x0", entropy));
+
+        // every secret was dropped rather than left behind somewhere, and nothing but zeros was
+        // left in its memory when it was released
+        assert_eq!(live(), 0);
+        assert_eq!(dirty(), 0);
+    }
 
-    #[ignore]
     #[test]
     fn generate_g1point_from_random_key() {
-        let power = "97f1d3a73197d7942695638c4fa9ac0fc3688c4f9774b905a14e3a3f171bac586c55e83ff97a1aeffb3af00adb22c6bb";
-        let point = g1_point_from_compressed(&bytes_from_hex_str(power)).unwrap();
-        let compressed = hex::encode(point.to_compressed());
+        let power = "0x97f1d3a73197d7942695638c4fa9ac0fc3688c4f9774b905a14e3a3f171bac586c55e83ff97a1aeffb3af00adb22c6bb";
+        let point = power.parse::<G1Point>().unwrap();
+        assert_eq!(point, G1Point::generator());
+        assert_eq!(point.to_string(), power);
+    }
+
+    /// A genesis transcript of real-world size, as written by `majlis-kzg init`.
+    fn initial_contribution() -> BatchContribution {
+        let content = fs::read_to_string("initialTranscript.json").unwrap();
+        let transcript = serde_json::from_str::<BatchTranscript>(&content).unwrap();
+        current_contribution(&transcript)
     }
 
     #[ignore]
     #[test]
     fn update_powers_of_tau() {
-        let content = fs::read_to_string("initialTranscript.json").unwrap();
-        let contribution = serde_json::from_str::<BatchContribution>(&content).unwrap();
+        let contribution = initial_contribution();
 
-        let random = generate_random_scalar();
-        super::update_powers_of_tau(contribution.contributions[0].clone(), random);
+        let secrets = Entropy::new().into_secrets(1);
+        super::update_powers_of_tau(&mut contribution.contributions[0].clone(), &secrets[0]);
     }
 
     #[ignore]
     #[test]
     fn check_subgroup_contribution_file() {
-        let content = fs::read_to_string("initialTranscript.json").unwrap();
-        let contribution = serde_json::from_str::<BatchContribution>(&content).unwrap();
-        assert!(subgroup_checks(contribution));
+        let contribution = initial_contribution();
+        assert!(subgroup_checks(&contribution).is_ok());
     }
 }
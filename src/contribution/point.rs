@@ -0,0 +1,106 @@
+use bls12_381::{G1Affine, G2Affine};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use std::{fmt, str::FromStr};
+
+use super::utils::{
+    bytes_from_hex_str, g1_point_from_compressed_unchecked, g2_point_from_compressed_unchecked,
+    PointError,
+};
+
+/// Defines a newtype over a curve point that (de)serializes from the `0x`-prefixed hex of its
+/// compressed form, which is how the sequencer encodes points.
+///
+/// Parsing only checks that the bytes decode to a point on the curve. Subgroup membership is much
+/// more expensive to check point by point and is left to [`subgroup_checks`](super::subgroup_checks).
+macro_rules! point_impl {
+    ($name:ident, $affine:ident, $decompress:ident, $group:literal) => {
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        pub struct $name(pub $affine);
+
+        impl $name {
+            pub fn generator() -> Self {
+                Self($affine::generator())
+            }
+
+            pub fn identity() -> Self {
+                Self($affine::identity())
+            }
+        }
+
+        impl From<$affine> for $name {
+            fn from(point: $affine) -> Self {
+                Self(point)
+            }
+        }
+
+        impl FromStr for $name {
+            type Err = PointError;
+
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                $decompress(&bytes_from_hex_str(s)?).map(Self)
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write!(f, "0x{}", hex::encode(self.0.to_compressed()))
+            }
+        }
+
+        impl Serialize for $name {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.collect_str(self)
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $name {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                let s = String::deserialize(deserializer)?;
+                s.parse().map_err(|e| {
+                    de::Error::custom(format!(
+                        concat!("invalid ", $group, " point `{}`: {}"),
+                        s, e
+                    ))
+                })
+            }
+        }
+    };
+}
+
+point_impl!(G1Point, G1Affine, g1_point_from_compressed_unchecked, "G1");
+point_impl!(G2Point, G2Affine, g2_point_from_compressed_unchecked, "G2");
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn roundtrip_generator() {
+        let json = serde_json::to_string(&G1Point::generator()).unwrap();
+        assert_eq!(json, "\"0x97f1d3a73197d7942695638c4fa9ac0fc3688c4f9774b905a14e3a3f171bac586c55e83ff97a1aeffb3af00adb22c6bb\"");
+        assert_eq!(
+            serde_json::from_str::<G1Point>(&json).unwrap(),
+            G1Point::generator()
+        );
+
+        let json = serde_json::to_string(&G2Point::generator()).unwrap();
+        assert_eq!(
+            serde_json::from_str::<G2Point>(&json).unwrap(),
+            G2Point::generator()
+        );
+    }
+
+    #[test]
+    fn reject_malformed_points() {
+        // odd-length hex
+        assert!(serde_json::from_str::<G1Point>("\"0x97f\"").is_err());
+        // a G1 point is not a G2 point
+        assert!(serde_json::from_str::<G2Point>(
+            &serde_json::to_string(&G1Point::generator()).unwrap()
+        )
+        .is_err());
+        // there is no y with y^2 = 1^3 + 4
+        let not_on_curve = format!("\"0x80{}01\"", "00".repeat(46));
+        assert!(serde_json::from_str::<G1Point>(&not_on_curve).is_err());
+    }
+}
@@ -0,0 +1,192 @@
+use color_eyre::Result;
+use std::{
+    alloc::{self, Layout},
+    fmt,
+    ptr::NonNull,
+};
+use zeroize::Zeroize;
+
+/// Heap cell for toxic waste.
+///
+/// The value lives on its own page(s), which are `mlock`ed so they are never swapped to disk, and
+/// is zeroized before the memory is released. Locking is best effort : if the OS refuses (eg.
+/// because of `RLIMIT_MEMLOCK`), the value is still zeroized on drop.
+pub struct Secret<T: Zeroize> {
+    ptr: NonNull<T>,
+    layout: Layout,
+    locked: bool,
+}
+
+// SAFETY: `Secret` owns its allocation exclusively, just like a `Box<T>`.
+unsafe impl<T: Zeroize + Send> Send for Secret<T> {}
+unsafe impl<T: Zeroize + Sync> Sync for Secret<T> {}
+
+impl<T: Zeroize> Secret<T> {
+    /// Moves `value` into guarded memory. Moving copies it through the stack, where it can't be
+    /// wiped, so `value` shouldn't be secret yet: derive the secret in place through
+    /// [`Secret::expose_mut`].
+    pub fn new(value: T) -> Self {
+        let page = page_size();
+        let size = std::mem::size_of::<T>().max(1);
+        let layout = Layout::from_size_align(size.div_ceil(page) * page, page)
+            .expect("secret must fit in memory");
+
+        // SAFETY: the layout has a non-zero size.
+        let ptr = unsafe { alloc::alloc_zeroed(layout) } as *mut T;
+        let Some(ptr) = NonNull::new(ptr) else {
+            alloc::handle_alloc_error(layout)
+        };
+
+        let locked = lock(ptr.as_ptr() as *const u8, layout.size());
+
+        // SAFETY: `ptr` is valid and aligned for `T` since the page alignment is a multiple of `T`'s.
+        unsafe { ptr.as_ptr().write(value) };
+
+        #[cfg(test)]
+        tests::LIVE.with(|live| live.set(live.get() + 1));
+
+        Self {
+            ptr,
+            layout,
+            locked,
+        }
+    }
+
+    pub fn expose(&self) -> &T {
+        // SAFETY: `ptr` was initialized in `new` and is only freed on drop.
+        unsafe { self.ptr.as_ref() }
+    }
+
+    pub fn expose_mut(&mut self) -> &mut T {
+        // SAFETY: see `expose`.
+        unsafe { self.ptr.as_mut() }
+    }
+}
+
+impl<T: Zeroize> Drop for Secret<T> {
+    fn drop(&mut self) {
+        self.expose_mut().zeroize();
+
+        #[cfg(test)]
+        {
+            // SAFETY: the allocation is live until it's deallocated below.
+            let memory = unsafe {
+                std::slice::from_raw_parts(self.ptr.as_ptr() as *const u8, self.layout.size())
+            };
+            tests::released(memory);
+        }
+
+        // SAFETY: the value was zeroized and is never used again.
+        unsafe {
+            std::ptr::drop_in_place(self.ptr.as_ptr());
+
+            if self.locked {
+                libc::munlock(self.ptr.as_ptr() as *const libc::c_void, self.layout.size());
+            }
+
+            alloc::dealloc(self.ptr.as_ptr() as *mut u8, self.layout);
+        }
+    }
+}
+
+impl<T: Zeroize> fmt::Debug for Secret<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Secret(***)")
+    }
+}
+
+/// Disables core dumps for the rest of the process so a crash while contributing can't write the
+/// secrets to disk.
+pub fn disable_core_dumps() -> Result<()> {
+    let limit = libc::rlimit {
+        rlim_cur: 0,
+        rlim_max: 0,
+    };
+
+    // SAFETY: `limit` is a valid `rlimit`.
+    if unsafe { libc::setrlimit(libc::RLIMIT_CORE, &limit) } != 0 {
+        return Err(std::io::Error::last_os_error().into());
+    }
+
+    // also keeps other processes of the same user from attaching to us
+    #[cfg(target_os = "linux")]
+    {
+        // SAFETY: `PR_SET_DUMPABLE` only takes an integer argument.
+        if unsafe { libc::prctl(libc::PR_SET_DUMPABLE, 0) } != 0 {
+            return Err(std::io::Error::last_os_error().into());
+        }
+    }
+
+    Ok(())
+}
+
+fn page_size() -> usize {
+    // SAFETY: `sysconf` has no preconditions.
+    match unsafe { libc::sysconf(libc::_SC_PAGESIZE) } {
+        size if size > 0 => size as usize,
+        _ => 4096,
+    }
+}
+
+fn lock(ptr: *const u8, len: usize) -> bool {
+    // SAFETY: `ptr..ptr + len` is a live allocation owned by the caller.
+    unsafe { libc::mlock(ptr as *const libc::c_void, len) == 0 }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use bls12_381::Scalar;
+    use std::cell::Cell;
+
+    thread_local! {
+        /// Secrets created minus secrets dropped on this thread.
+        pub(crate) static LIVE: Cell<isize> = const { Cell::new(0) };
+        /// Secrets dropped on this thread whose memory wasn't all zeros when it was released.
+        pub(crate) static DIRTY: Cell<usize> = const { Cell::new(0) };
+    }
+
+    /// Looks at the memory of a secret as it's about to be released.
+    pub(super) fn released(memory: &[u8]) {
+        LIVE.with(|live| live.set(live.get() - 1));
+        if memory.iter().any(|&byte| byte != 0) {
+            DIRTY.with(|dirty| dirty.set(dirty.get() + 1));
+        }
+    }
+
+    /// Claims to be wiped without doing it, to check that it doesn't go unnoticed.
+    struct Leaky([u8; 32]);
+
+    impl Zeroize for Leaky {
+        fn zeroize(&mut self) {}
+    }
+
+    #[test]
+    fn zeroize_in_place() {
+        let mut secret = Secret::new(Scalar::from(42));
+        assert_eq!(*secret.expose(), Scalar::from(42));
+
+        secret.expose_mut().zeroize();
+        assert_eq!(secret.expose().to_bytes(), [0u8; 32]);
+    }
+
+    #[test]
+    fn memory_is_wiped_on_drop() {
+        let dirty = || DIRTY.with(Cell::get);
+
+        drop(Secret::new(Scalar::from(42)));
+        drop(Secret::new([7u8; 64]));
+        assert_eq!(dirty(), 0);
+
+        let leaky = Secret::new(Leaky([7u8; 32]));
+        assert_eq!(leaky.expose().0, [7u8; 32]);
+        drop(leaky);
+        assert_eq!(dirty(), 1);
+    }
+
+    #[test]
+    fn debug_hides_value() {
+        let secret = Secret::new([7u8; 64]);
+        assert_eq!(format!("{secret:?}"), "Secret(***)");
+    }
+}
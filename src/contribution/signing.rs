@@ -1,4 +1,6 @@
 #[cfg(feature = "eth")]
+use super::point::G2Point;
+#[cfg(all(feature = "eth", test))]
 use super::types::BatchContribution;
 #[cfg(feature = "eth")]
 use ethers::core::types::transaction::eip712::TypedData;
@@ -7,39 +9,52 @@ use serde_json::{json, Value};
 
 use bls12_381::Scalar;
 use blsful::{SecretKey, Signature};
-use color_eyre::{eyre::ensure, Result};
-
-use crate::contribution::identity::{eth_address_to_identity, github_handle_to_identity};
+#[cfg(feature = "eth")]
+use color_eyre::Result;
 
-pub async fn sign_identity<T: AsRef<str>>(x: Scalar, identity: T) -> Result<Signature> {
-    let identity = identity.as_ref();
+use super::secret::Secret;
 
-    ensure!(
-        identity.strip_prefix("0x").is_some() || identity.strip_prefix("@").is_some(),
-        "unknown identity format"
-    );
+/// Signs the resolved identity (see [`resolve_identity`](super::identity::resolve_identity))
+/// with the secret `x` of a sub-ceremony.
+pub fn sign_identity<T: AsRef<str>>(x: &Secret<Scalar>, identity: T) -> Signature {
+    // `Scalar` serializes to little-endian while `SecretKey` expects big-endian bytes
+    let mut bytes = Secret::new([0u8; 32]);
+    *bytes.expose_mut() = x.expose().to_bytes();
+    bytes.expose_mut().reverse();
 
-    let encoded_iden: Vec<u8> = if identity.strip_prefix("0x").is_some() {
-        eth_address_to_identity(identity)?.as_bytes().into()
-    } else {
-        github_handle_to_identity(identity).await?.as_bytes().into()
-    };
+    // `SecretKey` zeroizes itself on drop
+    let sk = SecretKey::from_bytes(bytes.expose()).unwrap();
 
-    let sk = SecretKey::from_bytes(&x.to_bytes()).unwrap();
-    Ok(Signature::new(&sk, encoded_iden).unwrap())
+    Signature::new(&sk, identity.as_ref().as_bytes()).unwrap()
 }
 
-#[cfg(feature = "eth")]
+/// Typed data over the pubkeys of a contribution, as built by [`construct_pubkeys_eip712_typed_data`].
+#[cfg(all(feature = "eth", test))]
 pub fn construct_contribution_eip712_typed_data(
     batch_contribution: &BatchContribution,
 ) -> Result<TypedData> {
+    construct_pubkeys_eip712_typed_data(
+        batch_contribution
+            .contributions
+            .iter()
+            .map(|contr| (contr.num_g1_powers, contr.num_g2_powers, &contr.pot_pubkey)),
+    )
+}
+
+/// Typed data over the `(numG1Powers, numG2Powers, potPubkey)` of every sub-ceremony, which is
+/// what participants sign with their Ethereum account.
+#[cfg(feature = "eth")]
+pub fn construct_pubkeys_eip712_typed_data<'a, I>(pubkeys: I) -> Result<TypedData>
+where
+    I: IntoIterator<Item = (i32, i32, &'a G2Point)>,
+{
     let mut keys: Vec<Value> = vec![];
 
-    for contr in batch_contribution.contributions.iter() {
+    for (num_g1_powers, num_g2_powers, pot_pubkey) in pubkeys {
         keys.push(json!({
-            "numG1Powers": contr.num_g1_powers,
-            "numG2Powers": contr.num_g2_powers,
-            "potPubkey": contr.pot_pubkey
+            "numG1Powers": num_g1_powers,
+            "numG2Powers": num_g2_powers,
+            "potPubkey": pot_pubkey
         }));
     }
 
@@ -73,13 +88,27 @@ pub fn construct_contribution_eip712_typed_data(
     serde_json::from_value(json).map_err(|e| color_eyre::eyre::eyre!(e))
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "eth"))]
 mod tests {
     use super::*;
-    use serde_json::json;
-    use std::fs;
+    use crate::contribution::tests::genesis_batch;
+
+    #[test]
+    fn typed_data_lists_every_pubkey() {
+        let batch = genesis_batch(&[(8, 3), (16, 3)]);
+        let typed_data = construct_contribution_eip712_typed_data(&batch).unwrap();
+
+        let pubkey = G2Point::generator().to_string();
+        assert_eq!(typed_data.primary_type, "PoTPubkeys");
+        assert_eq!(
+            typed_data.message["potPubkeys"],
+            json!([
+                { "numG1Powers": 8, "numG2Powers": 3, "potPubkey": pubkey },
+                { "numG1Powers": 16, "numG2Powers": 3, "potPubkey": pubkey },
+            ])
+        );
+    }
 
-    #[cfg(feature = "eth")]
     #[ignore]
     #[test]
     fn prepare_message_for_signing() {
@@ -0,0 +1,269 @@
+use bls12_381::{G1Affine, G1Projective, G2Affine, G2Projective};
+use rand_chacha::ChaCha20Rng;
+use rand_core::{RngCore, SeedableRng};
+use rayon::prelude::*;
+use std::{fmt, ops::Add};
+
+use super::point::{G1Point, G2Point};
+
+/// Number of random subsets of the points whose sums have to be in the prime-order subgroup.
+///
+/// Whatever the order of a component outside the subgroup, including the small primes dividing
+/// the cofactors, it cancels out of the sum of a random subset with probability at most 1/2. A
+/// point with such a component therefore passes every round with probability at most 2^-40.
+const ROUNDS: usize = 40;
+
+/// Bits of the coefficients that are processed at once when combining the points.
+const WINDOW: usize = 8;
+
+/// Points are combined in chunks of this size in parallel.
+const CHUNK_SIZE: usize = 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Group {
+    G1,
+    G2,
+}
+
+impl fmt::Display for Group {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::G1 => write!(f, "G1"),
+            Self::G2 => write!(f, "G2"),
+        }
+    }
+}
+
+/// A power of a sub-ceremony that isn't in the prime-order subgroup.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("sub-ceremony #{ceremony}: {group} power #{index} is not in the prime-order subgroup")]
+pub struct SubgroupError {
+    pub ceremony: usize,
+    pub group: Group,
+    pub index: usize,
+}
+
+pub trait BatchCheck: Copy + Send + Sync {
+    type Projective: Copy
+        + Send
+        + Sync
+        + Add<Output = Self::Projective>
+        + Add<Self, Output = Self::Projective>;
+
+    fn identity() -> Self::Projective;
+    fn double(point: &Self::Projective) -> Self::Projective;
+    fn is_torsion_free(&self) -> bool;
+    fn is_projective_torsion_free(point: &Self::Projective) -> bool;
+}
+
+macro_rules! batch_check_impl {
+    ($point:ident, $affine:ident, $projective:ident) => {
+        impl Add<$point> for $projective {
+            type Output = $projective;
+
+            fn add(self, rhs: $point) -> $projective {
+                self + rhs.0
+            }
+        }
+
+        impl BatchCheck for $point {
+            type Projective = $projective;
+
+            fn identity() -> $projective {
+                $projective::identity()
+            }
+
+            fn double(point: &$projective) -> $projective {
+                point.double()
+            }
+
+            fn is_torsion_free(&self) -> bool {
+                self.0.is_torsion_free().into()
+            }
+
+            fn is_projective_torsion_free(point: &$projective) -> bool {
+                $affine::from(point).is_torsion_free().into()
+            }
+        }
+    };
+}
+
+batch_check_impl!(G1Point, G1Affine, G1Projective);
+batch_check_impl!(G2Point, G2Affine, G2Projective);
+
+/// Checks that every point is in the prime-order subgroup by checking the sums of random subsets
+/// of them, which is much cheaper than checking the points one by one. See [`ROUNDS`] for what
+/// that guarantees. If a sum fails, the points are checked one by one and the index of the first
+/// invalid point is returned.
+pub fn batch_subgroup_check<P: BatchCheck>(points: &[P]) -> Result<(), usize> {
+    let mut rng = ChaCha20Rng::from_entropy();
+    // bit `round` of a point's entry tells whether it's in the subset of that round
+    let subsets = (0..points.len())
+        .map(|_| rng.next_u64())
+        .collect::<Vec<u64>>();
+
+    let sums = points
+        .par_chunks(CHUNK_SIZE)
+        .zip(subsets.par_chunks(CHUNK_SIZE))
+        .map(|(points, subsets)| subset_sums(points, subsets))
+        .reduce(
+            || [P::identity(); ROUNDS],
+            |mut a, b| {
+                for (a, b) in a.iter_mut().zip(b) {
+                    *a = *a + b;
+                }
+                a
+            },
+        );
+
+    if !sums.iter().all(P::is_projective_torsion_free) {
+        // a sum of points from the subgroup is always in the subgroup
+        return Err(points
+            .par_iter()
+            .position_first(|point| !point.is_torsion_free())
+            .expect("a point must be outside the subgroup"));
+    }
+
+    Ok(())
+}
+
+/// Computes the sum of the subset of `points` of every round.
+fn subset_sums<P: BatchCheck>(points: &[P], subsets: &[u64]) -> [P::Projective; ROUNDS] {
+    let mut sums = [P::identity(); ROUNDS];
+
+    for (point, subset) in points.iter().zip(subsets) {
+        for (round, sum) in sums.iter_mut().enumerate() {
+            if subset >> round & 1 == 1 {
+                *sum = *sum + *point;
+            }
+        }
+    }
+
+    sums
+}
+
+/// Computes `Σ coefficients[i] * points[i]` with the bucket method.
+pub fn linear_combination<P: BatchCheck>(points: &[P], coefficients: &[u64]) -> P::Projective {
+    let mut acc = P::identity();
+
+    for window in (0..u64::BITS as usize / WINDOW).rev() {
+        for _ in 0..WINDOW {
+            acc = P::double(&acc);
+        }
+
+        let mut buckets = vec![P::identity(); (1 << WINDOW) - 1];
+        for (point, coefficient) in points.iter().zip(coefficients) {
+            let digit = (coefficient >> (window * WINDOW)) as usize & ((1 << WINDOW) - 1);
+            if digit != 0 {
+                buckets[digit - 1] = buckets[digit - 1] + *point;
+            }
+        }
+
+        // `Σ digit * bucket[digit]` as a running sum of running sums
+        let mut running = P::identity();
+        let mut sum = P::identity();
+        for bucket in buckets.into_iter().rev() {
+            running = running + bucket;
+            sum = sum + running;
+        }
+
+        acc = acc + sum;
+    }
+
+    acc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Distinct multiples of the generator, cheaper to compute than random ones.
+    fn g1_points(n: usize) -> Vec<G1Point> {
+        let g = G1Projective::generator();
+        let mut p = g;
+
+        (0..n)
+            .map(|_| {
+                p = p.double() + g;
+                G1Point(G1Affine::from(p))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn linear_combination_matches_naive() {
+        let points = g1_points(10);
+        let coefficients = (0..10u64)
+            .map(|i| i * 0x0101_0101_0101 + 1)
+            .collect::<Vec<_>>();
+
+        let expected = points
+            .iter()
+            .zip(&coefficients)
+            .fold(G1Projective::identity(), |acc, (p, c)| {
+                acc + p.0 * bls12_381::Scalar::from(*c)
+            });
+
+        assert_eq!(linear_combination(&points, &coefficients), expected);
+    }
+
+    #[test]
+    fn accept_subgroup_points() {
+        assert_eq!(batch_subgroup_check(&g1_points(1500)), Ok(()));
+        assert_eq!(batch_subgroup_check(&[G2Point::generator(); 65]), Ok(()));
+    }
+
+    /// (4, y) is on the curve but, like almost every point of the curve, not in the subgroup.
+    fn outside() -> G1Point {
+        let mut compressed = [0u8; 48];
+        compressed[0] = 0x80;
+        compressed[47] = 4;
+        G1Point(Option::from(G1Affine::from_compressed_unchecked(&compressed)).unwrap())
+    }
+
+    #[test]
+    fn locate_point_outside_subgroup() {
+        let mut points = g1_points(1500);
+        points[1337] = outside();
+
+        assert_eq!(batch_subgroup_check(&points), Err(1337));
+    }
+
+    #[test]
+    fn reject_small_torsion() {
+        // multiplying by the order of the curve over 3 leaves only the order 3 component
+        let order_over_3 = hex::decode(
+            "08ab05f8bdd54cde190937e76bc3e447cc27c3d6fbd7063fcd104635a790520c\
+             0a395554e5c6aaaad955555555558e39",
+        )
+        .unwrap();
+        let torsion = (0..=u8::MAX)
+            .filter_map(|x| {
+                let mut compressed = [0u8; 48];
+                compressed[0] = 0x80;
+                compressed[47] = x;
+                Option::<G1Affine>::from(G1Affine::from_compressed_unchecked(&compressed))
+            })
+            .map(|point| {
+                order_over_3
+                    .iter()
+                    .flat_map(|byte| (0..8).rev().map(move |bit| byte >> bit & 1 == 1))
+                    .fold(G1Projective::identity(), |acc, bit| match bit {
+                        true => acc.double() + point,
+                        false => acc.double(),
+                    })
+            })
+            .find(|torsion| !bool::from(torsion.is_identity()))
+            .unwrap();
+        assert!(bool::from((torsion.double() + torsion).is_identity()));
+
+        let mut points = g1_points(100);
+        points[42] = G1Point(G1Affine::from(points[42].0 + torsion));
+        assert!(!points[42].is_torsion_free());
+
+        // an order 3 component would cancel out of a random linear combination a third of the time
+        for _ in 0..10 {
+            assert_eq!(batch_subgroup_check(&points), Err(42));
+        }
+    }
+}
@@ -1,24 +1,26 @@
 use serde::{Deserialize, Serialize};
 
+use super::point::{G1Point, G2Point};
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct TranscriptPowersOfTau {
     #[serde(rename = "G1Powers")]
-    pub g1_powers: Vec<String>,
+    pub g1_powers: Vec<G1Point>,
     #[serde(rename = "G2Powers")]
-    pub g2_powers: Vec<String>,
+    pub g2_powers: Vec<G2Point>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Witness {
     #[serde(rename = "runningProducts")]
-    pub running_products: Vec<String>,
+    pub running_products: Vec<G1Point>,
     #[serde(rename = "potPubkeys")]
-    pub pot_pubkeys: Vec<String>,
+    pub pot_pubkeys: Vec<G2Point>,
     #[serde(rename = "blsSignatures")]
     pub bls_signatures: Vec<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Transcript {
     #[serde(rename = "numG1Powers")]
     pub num_g1_powers: i32,
@@ -29,7 +31,7 @@ pub struct Transcript {
     pub witness: Witness,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct BatchTranscript {
     pub transcripts: Vec<Transcript>,
     #[serde(rename = "participantIds")]
@@ -40,21 +42,29 @@ pub struct BatchTranscript {
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct PowersOfTau {
-    pub g1_powers: Vec<String>,
-    pub g2_powers: Vec<String>,
+    #[serde(rename = "G1Powers")]
+    pub g1_powers: Vec<G1Point>,
+    #[serde(rename = "G2Powers")]
+    pub g2_powers: Vec<G2Point>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Contribution {
+    #[serde(rename = "numG1Powers")]
     pub num_g1_powers: i32,
+    #[serde(rename = "numG2Powers")]
     pub num_g2_powers: i32,
+    #[serde(rename = "powersOfTau")]
     pub powers_of_tau: PowersOfTau,
-    pub pot_pubkey: String,
+    #[serde(rename = "potPubkey")]
+    pub pot_pubkey: G2Point,
+    #[serde(rename = "blsSignature")]
     pub bls_signature: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct BatchContribution {
     pub contributions: Vec<Contribution>,
+    #[serde(rename = "ecdsaSignature")]
     pub ecdsa_signature: Option<String>,
 }
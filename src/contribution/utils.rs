@@ -1,16 +1,97 @@
 use bls12_381::{G1Affine, G2Affine};
+use thiserror::Error;
 
+#[cfg_attr(test, derive(PartialEq))]
+#[derive(Debug, Error)]
+pub enum PointError {
+    #[error("invalid hex: {0}")]
+    InvalidHex(#[from] hex::FromHexError),
+    #[error("expected {expected} bytes, got {actual}")]
+    InvalidLength { expected: usize, actual: usize },
+    #[error("point is not on the curve")]
+    NotOnCurve,
+}
+
+/// Decompresses a G1 point, only checking that it is on the curve (see
+/// [`subgroup_checks`](super::subgroup_checks)).
 #[inline]
-pub fn g1_point_from_compressed(compressed_g1_point: &[u8]) -> Option<G1Affine> {
-    G1Affine::from_compressed(unsafe { &*(compressed_g1_point.as_ptr() as *const [u8; 48]) }).into()
+pub fn g1_point_from_compressed_unchecked(
+    compressed_g1_point: &[u8],
+) -> Result<G1Affine, PointError> {
+    let compressed = to_array::<48>(compressed_g1_point)?;
+    Option::from(G1Affine::from_compressed_unchecked(&compressed)).ok_or(PointError::NotOnCurve)
 }
 
+/// Decompresses a G2 point, only checking that it is on the curve.
 #[inline]
-pub fn g2_point_from_compressed(compressed_g2_point: &[u8]) -> Option<G2Affine> {
-    G2Affine::from_compressed(unsafe { &*(compressed_g2_point.as_ptr() as *const [u8; 96]) }).into()
+pub fn g2_point_from_compressed_unchecked(
+    compressed_g2_point: &[u8],
+) -> Result<G2Affine, PointError> {
+    let compressed = to_array::<96>(compressed_g2_point)?;
+    Option::from(G2Affine::from_compressed_unchecked(&compressed)).ok_or(PointError::NotOnCurve)
 }
 
 #[inline]
-pub fn bytes_from_hex_str(hex: &str) -> Vec<u8> {
-    hex::decode(hex.strip_prefix("0x").unwrap_or(hex)).expect("must able to parse hex string")
+pub fn bytes_from_hex_str(hex: &str) -> Result<Vec<u8>, PointError> {
+    Ok(hex::decode(hex.strip_prefix("0x").unwrap_or(hex))?)
+}
+
+fn to_array<const N: usize>(bytes: &[u8]) -> Result<[u8; N], PointError> {
+    bytes.try_into().map_err(|_| PointError::InvalidLength {
+        expected: N,
+        actual: bytes.len(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reject_wrong_length() {
+        assert_eq!(
+            g1_point_from_compressed_unchecked(&[0x80; 47]),
+            Err(PointError::InvalidLength {
+                expected: 48,
+                actual: 47
+            })
+        );
+        assert_eq!(
+            g2_point_from_compressed_unchecked(&[0x80; 48]),
+            Err(PointError::InvalidLength {
+                expected: 96,
+                actual: 48
+            })
+        );
+    }
+
+    #[test]
+    fn reject_bad_hex() {
+        assert!(matches!(
+            bytes_from_hex_str("0x97f"),
+            Err(PointError::InvalidHex(_))
+        ));
+        assert!(matches!(
+            bytes_from_hex_str("0xzz"),
+            Err(PointError::InvalidHex(_))
+        ));
+    }
+
+    #[test]
+    fn reject_invalid_points() {
+        // there is no y with y^2 = 1^3 + 4
+        let mut not_on_curve = [0u8; 48];
+        not_on_curve[0] = 0x80;
+        not_on_curve[47] = 1;
+        assert_eq!(
+            g1_point_from_compressed_unchecked(&not_on_curve),
+            Err(PointError::NotOnCurve)
+        );
+
+        // (0, 2) is on the curve but has order 3, which is left to the subgroup checks
+        let mut small_order = [0u8; 48];
+        small_order[0] = 0x80;
+        let point = g1_point_from_compressed_unchecked(&small_order).unwrap();
+        assert!(!bool::from(point.is_torsion_free()));
+    }
 }
@@ -0,0 +1,260 @@
+use std::collections::HashSet;
+
+use super::{
+    point::{G1Point, G2Point},
+    types::{BatchContribution, Contribution},
+};
+use crate::sequencer::types::{CeremoniesError, CeremonyError, ContributionError};
+
+/// Runs the checks the sequencer runs on a contribution before accepting it, so a contribution
+/// that would be rejected is caught before it costs us our slot in the lobby.
+///
+/// `previous` is the batch received from the sequencer and `updated` is our contribution on top
+/// of it. The pairing checks are done by [`verify_update`](super::verification::verify_update).
+pub fn validate_contribution(
+    previous: &BatchContribution,
+    updated: &BatchContribution,
+) -> Result<(), ContributionError> {
+    if previous.contributions.len() != updated.contributions.len() {
+        return Err(ContributionError::Ceremonies(
+            CeremoniesError::UnexpectedNumContributions,
+        ));
+    }
+
+    previous
+        .contributions
+        .iter()
+        .zip(updated.contributions.iter())
+        .try_for_each(|(previous, updated)| validate_sub_contribution(previous, updated))
+        .map_err(ContributionError::Ceremony)
+}
+
+/// Checks that every sub-ceremony of a batch received from the sequencer holds as many powers as
+/// it claims, before we contribute to it.
+pub fn validate_batch_shape(batch: &BatchContribution) -> Result<(), ContributionError> {
+    batch
+        .contributions
+        .iter()
+        .try_for_each(validate_shape)
+        .map_err(ContributionError::Ceremony)
+}
+
+fn validate_sub_contribution(
+    previous: &Contribution,
+    updated: &Contribution,
+) -> Result<(), CeremonyError> {
+    // `previous` comes from the sequencer, and is indexed below just as much as `updated`
+    validate_shape(previous)?;
+    validate_shape(updated)?;
+    if updated.num_g1_powers != previous.num_g1_powers {
+        return Err(CeremonyError::UnexpectedNumG1Powers);
+    }
+    if updated.num_g2_powers != previous.num_g2_powers {
+        return Err(CeremonyError::UnexpectedNumG2Powers);
+    }
+
+    let g1_powers = &updated.powers_of_tau.g1_powers;
+    let g2_powers = &updated.powers_of_tau.g2_powers;
+
+    if g1_powers[0] != G1Point::generator() {
+        return Err(CeremonyError::InvalidG1FirstValue);
+    }
+    if g2_powers[0] != G2Point::generator() {
+        return Err(CeremonyError::InvalidG2FirstValue);
+    }
+
+    if g1_powers.iter().any(|p| bool::from(p.0.is_identity())) {
+        return Err(CeremonyError::ZeroG1);
+    }
+    if g2_powers.iter().any(|p| bool::from(p.0.is_identity())) {
+        return Err(CeremonyError::ZeroG2);
+    }
+
+    if bool::from(updated.pot_pubkey.0.is_identity()) {
+        return Err(CeremonyError::ZeroPubkey);
+    }
+    if !bool::from(updated.pot_pubkey.0.is_torsion_free()) {
+        return Err(CeremonyError::InvalidPubKey);
+    }
+
+    // a secret of one leaves everything where it was
+    if updated.pot_pubkey == G2Point::generator()
+        || g1_powers[1] == previous.powers_of_tau.g1_powers[1]
+    {
+        return Err(CeremonyError::ContributionNoEntropy);
+    }
+
+    if g1_powers[1] == G1Point::generator() {
+        return Err(CeremonyError::InvalidG1One);
+    }
+    if g2_powers[1] == G2Point::generator() {
+        return Err(CeremonyError::InvalidG2One);
+    }
+
+    if has_duplicates(g1_powers.iter().map(|p| p.0.to_compressed())) {
+        return Err(CeremonyError::DuplicateG1);
+    }
+    if has_duplicates(g2_powers.iter().map(|p| p.0.to_compressed())) {
+        return Err(CeremonyError::DuplicateG2);
+    }
+
+    Ok(())
+}
+
+/// Checks that `contribution` holds as many powers as it claims, so they can be indexed.
+fn validate_shape(contribution: &Contribution) -> Result<(), CeremonyError> {
+    let num_g1 = contribution.num_g1_powers;
+    let num_g2 = contribution.num_g2_powers;
+
+    // the first power of tau carries the running product, so each group needs at least two
+    if num_g1 < 2 {
+        return Err(CeremonyError::UnsupportedNumG1Powers);
+    }
+    if num_g2 < 2 {
+        return Err(CeremonyError::UnsupportedNumG2Powers);
+    }
+    if num_g2 > num_g1 {
+        return Err(CeremonyError::UnsupportedMoreG2Powers);
+    }
+    if contribution.powers_of_tau.g1_powers.len() != num_g1 as usize {
+        return Err(CeremonyError::UnexpectedNumG1Powers);
+    }
+    if contribution.powers_of_tau.g2_powers.len() != num_g2 as usize {
+        return Err(CeremonyError::InconsistentNumG2Powers);
+    }
+
+    Ok(())
+}
+
+fn has_duplicates<T: Eq + std::hash::Hash>(items: impl ExactSizeIterator<Item = T>) -> bool {
+    let mut seen = HashSet::with_capacity(items.len());
+    items.into_iter().any(|item| !seen.insert(item))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::contribution::{entropy::Entropy, tests::genesis_batch, update_batch};
+
+    fn updated(previous: &BatchContribution) -> BatchContribution {
+        let mut batch = previous.clone();
+        update_batch(&mut batch, "eth|0x0", Entropy::new());
+        batch
+    }
+
+    #[test]
+    fn accept_valid_contribution() {
+        let previous = genesis_batch(&[(8, 3), (16, 3)]);
+        assert_eq!(
+            validate_contribution(&previous, &updated(&previous)),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn reject_unexpected_num_contributions() {
+        let previous = genesis_batch(&[(8, 3), (16, 3)]);
+        let mut batch = updated(&previous);
+        batch.contributions.pop();
+
+        assert_eq!(
+            validate_contribution(&previous, &batch),
+            Err(ContributionError::Ceremonies(
+                CeremoniesError::UnexpectedNumContributions
+            ))
+        );
+    }
+
+    #[test]
+    fn reject_invalid_powers() {
+        let previous = genesis_batch(&[(8, 3)]);
+        let check = |modify: fn(&mut Contribution), expected: CeremonyError| {
+            let mut batch = updated(&previous);
+            modify(&mut batch.contributions[0]);
+            assert_eq!(
+                validate_contribution(&previous, &batch),
+                Err(ContributionError::Ceremony(expected))
+            );
+        };
+
+        check(
+            |c| {
+                c.powers_of_tau.g1_powers.pop();
+            },
+            CeremonyError::UnexpectedNumG1Powers,
+        );
+        check(
+            |c| c.num_g2_powers = 9,
+            CeremonyError::UnsupportedMoreG2Powers,
+        );
+        check(
+            |c| c.powers_of_tau.g1_powers.swap(0, 1),
+            CeremonyError::InvalidG1FirstValue,
+        );
+        check(
+            |c| c.powers_of_tau.g2_powers[2] = G2Point::identity(),
+            CeremonyError::ZeroG2,
+        );
+        check(
+            |c| c.pot_pubkey = G2Point::identity(),
+            CeremonyError::ZeroPubkey,
+        );
+        check(
+            |c| c.powers_of_tau.g1_powers[5] = c.powers_of_tau.g1_powers[4],
+            CeremonyError::DuplicateG1,
+        );
+    }
+
+    #[test]
+    fn reject_malformed_batch() {
+        let mut batch = genesis_batch(&[(8, 3), (16, 3)]);
+        assert_eq!(validate_batch_shape(&batch), Ok(()));
+
+        batch.contributions[1].num_g1_powers = 32;
+        assert_eq!(
+            validate_batch_shape(&batch),
+            Err(ContributionError::Ceremony(
+                CeremonyError::UnexpectedNumG1Powers
+            ))
+        );
+
+        batch.contributions[1].num_g1_powers = -16;
+        assert_eq!(
+            validate_batch_shape(&batch),
+            Err(ContributionError::Ceremony(
+                CeremonyError::UnsupportedNumG1Powers
+            ))
+        );
+    }
+
+    #[test]
+    fn reject_truncated_previous() {
+        let mut previous = genesis_batch(&[(8, 3)]);
+        let batch = updated(&previous);
+        previous.contributions[0]
+            .powers_of_tau
+            .g1_powers
+            .truncate(1);
+
+        assert_eq!(
+            validate_contribution(&previous, &batch),
+            Err(ContributionError::Ceremony(
+                CeremonyError::UnexpectedNumG1Powers
+            ))
+        );
+    }
+
+    #[test]
+    fn reject_contribution_without_entropy() {
+        let previous = genesis_batch(&[(8, 3)]);
+        let mut batch = previous.clone();
+        batch.contributions[0].pot_pubkey = G2Point::generator();
+
+        assert_eq!(
+            validate_contribution(&previous, &batch),
+            Err(ContributionError::Ceremony(
+                CeremonyError::ContributionNoEntropy
+            ))
+        );
+    }
+}
@@ -0,0 +1,193 @@
+use bls12_381::{multi_miller_loop, G1Affine, G2Affine, G2Prepared, Gt};
+use rand_chacha::ChaCha20Rng;
+use rand_core::{RngCore, SeedableRng};
+use std::fmt;
+
+use super::{
+    point::{G1Point, G2Point},
+    subgroup::linear_combination,
+    types::{BatchContribution, Contribution},
+};
+use crate::sequencer::types::CeremonyError;
+
+/// A pairing check that failed in one of the sub-ceremonies.
+#[cfg_attr(test, derive(PartialEq, Eq))]
+#[derive(Debug)]
+pub struct VerificationError {
+    pub ceremony: usize,
+    pub error: CeremonyError,
+}
+
+impl fmt::Display for VerificationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "sub-ceremony #{}: {}", self.ceremony, self.error)
+    }
+}
+
+impl std::error::Error for VerificationError {}
+
+/// Verifies our update of a batch before it is submitted :
+/// - Powers check - the G1 and G2 powers are successive powers of the same tau.
+/// - Pubkey check - `pot_pubkey` is the ratio between the old and the new first power of tau in G1.
+///   The first power in G1 is the running product, so this also checks that the new running
+///   product is the previous one multiplied by our secret.
+pub fn verify_update(
+    previous: &BatchContribution,
+    updated: &BatchContribution,
+) -> Result<(), VerificationError> {
+    previous
+        .contributions
+        .iter()
+        .zip(updated.contributions.iter())
+        .enumerate()
+        .try_for_each(|(ceremony, (previous, updated))| {
+            verify_sub_update(previous, updated)
+                .map_err(|error| VerificationError { ceremony, error })
+        })
+}
+
+fn verify_sub_update(previous: &Contribution, updated: &Contribution) -> Result<(), CeremonyError> {
+    verify_powers(
+        &updated.powers_of_tau.g1_powers,
+        &updated.powers_of_tau.g2_powers,
+    )?;
+
+    let (Some(previous_tau), Some(updated_tau)) = (
+        previous.powers_of_tau.g1_powers.get(1),
+        updated.powers_of_tau.g1_powers.get(1),
+    ) else {
+        return Err(CeremonyError::UnexpectedNumG1Powers);
+    };
+    if !verify_pubkey(previous_tau, updated_tau, &updated.pot_pubkey) {
+        return Err(CeremonyError::PubKeyPairingFailed);
+    }
+
+    Ok(())
+}
+
+/// Checks that `g1_powers` and `g2_powers` are `[1, tau, tau^2, ...]` for the same tau, with
+/// `e(g1[i + 1], g2[0]) == e(g1[i], g2[1])` and `e(g1[1], g2[i]) == e(g1[0], g2[i + 1])`.
+///
+/// Each set of equations is checked at once on a random linear combination of the powers, which
+/// holds for invalid powers with negligible probability.
+pub fn verify_powers(g1_powers: &[G1Point], g2_powers: &[G2Point]) -> Result<(), CeremonyError> {
+    if g1_powers.len() < 2 || g2_powers.len() < 2 {
+        return Ok(());
+    }
+
+    let mut rng = ChaCha20Rng::from_entropy();
+
+    let coefficients = random_coefficients(&mut rng, g1_powers.len() - 1);
+    let lhs = linear_combination(&g1_powers[1..], &coefficients);
+    let rhs = linear_combination(&g1_powers[..g1_powers.len() - 1], &coefficients);
+
+    if !pairings_match(&lhs.into(), &g2_powers[0].0, &rhs.into(), &g2_powers[1].0) {
+        return Err(CeremonyError::G1PairingFailed);
+    }
+
+    let coefficients = random_coefficients(&mut rng, g2_powers.len() - 1);
+    let lhs = linear_combination(&g2_powers[..g2_powers.len() - 1], &coefficients);
+    let rhs = linear_combination(&g2_powers[1..], &coefficients);
+
+    if !pairings_match(&g1_powers[1].0, &lhs.into(), &g1_powers[0].0, &rhs.into()) {
+        return Err(CeremonyError::G2PairingFailed);
+    }
+
+    Ok(())
+}
+
+/// Checks that `new = [x]previous` where `pubkey = [x]G2`.
+pub fn verify_pubkey(previous: &G1Point, new: &G1Point, pubkey: &G2Point) -> bool {
+    pairings_match(&new.0, &G2Affine::generator(), &previous.0, &pubkey.0)
+}
+
+/// `e(a1, b1) == e(a2, b2)`, computed with a single final exponentiation.
+pub fn pairings_match(a1: &G1Affine, b1: &G2Affine, a2: &G1Affine, b2: &G2Affine) -> bool {
+    multi_miller_loop(&[(a1, &G2Prepared::from(*b1)), (&-a2, &G2Prepared::from(*b2))])
+        .final_exponentiation()
+        == Gt::identity()
+}
+
+fn random_coefficients(rng: &mut ChaCha20Rng, n: usize) -> Vec<u64> {
+    (0..n).map(|_| rng.next_u64()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::contribution::{entropy::Entropy, tests::genesis_batch, update_batch};
+
+    fn updated(previous: &BatchContribution) -> BatchContribution {
+        let mut batch = previous.clone();
+        update_batch(&mut batch, "eth|0x0", Entropy::new());
+        batch
+    }
+
+    #[test]
+    fn accept_valid_update() {
+        let previous = genesis_batch(&[(8, 3), (16, 3)]);
+        let first = updated(&previous);
+        let second = updated(&first);
+
+        assert_eq!(verify_update(&previous, &first), Ok(()));
+        assert_eq!(verify_update(&first, &second), Ok(()));
+    }
+
+    #[test]
+    fn reject_tampered_powers() {
+        let previous = genesis_batch(&[(8, 3), (16, 3)]);
+
+        let mut batch = updated(&previous);
+        batch.contributions[1].powers_of_tau.g1_powers.swap(3, 4);
+        assert_eq!(
+            verify_update(&previous, &batch),
+            Err(VerificationError {
+                ceremony: 1,
+                error: CeremonyError::G1PairingFailed
+            })
+        );
+
+        let mut batch = updated(&previous);
+        batch.contributions[0].powers_of_tau.g2_powers[2] = G2Point::generator();
+        assert_eq!(
+            verify_update(&previous, &batch),
+            Err(VerificationError {
+                ceremony: 0,
+                error: CeremonyError::G2PairingFailed
+            })
+        );
+    }
+
+    #[test]
+    fn reject_mismatched_pubkey() {
+        let previous = genesis_batch(&[(8, 3), (16, 3)]);
+        let mut batch = updated(&previous);
+        batch.contributions[0].pot_pubkey = batch.contributions[1].pot_pubkey;
+
+        assert_eq!(
+            verify_update(&previous, &batch),
+            Err(VerificationError {
+                ceremony: 0,
+                error: CeremonyError::PubKeyPairingFailed
+            })
+        );
+    }
+
+    #[test]
+    fn reject_truncated_previous() {
+        let mut previous = genesis_batch(&[(8, 3)]);
+        let batch = updated(&previous);
+        previous.contributions[0]
+            .powers_of_tau
+            .g1_powers
+            .truncate(1);
+
+        assert_eq!(
+            verify_update(&previous, &batch),
+            Err(VerificationError {
+                ceremony: 0,
+                error: CeremonyError::UnexpectedNumG1Powers
+            })
+        );
+    }
+}
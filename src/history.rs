@@ -0,0 +1,241 @@
+use chrono::{DateTime, Utc};
+use color_eyre::Result;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    contribution::{point::G2Point, types::BatchContribution},
+    sequencer::types::{ContributionReceipt, ReceiptVerification},
+};
+
+/// Evidence of a single contribution attempt. It holds everything we sent to and received from
+/// the sequencer, but nothing the secrets could be recovered from.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LogEntry {
+    pub timestamp: DateTime<Utc>,
+    pub sequencer_url: String,
+    pub sequencer_address: Option<String>,
+    pub identity: String,
+    pub entropy_sources: Vec<String>,
+    pub ceremonies: Vec<SubCeremonyEntry>,
+    pub ecdsa_signature: Option<String>,
+    pub receipt: Option<ContributionReceipt>,
+    /// Whether the receipt was signed by the sequencer, if we got one.
+    #[serde(default)]
+    pub receipt_verification: Option<ReceiptVerification>,
+    pub outcome: Outcome,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SubCeremonyEntry {
+    pub num_g1_powers: i32,
+    pub num_g2_powers: i32,
+    pub pot_pubkey: G2Point,
+    pub bls_signature: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", tag = "status", content = "error")]
+pub enum Outcome {
+    /// The attempt didn't get past the lobby, or was interrupted.
+    Pending,
+    Contributed,
+    Failed(String),
+}
+
+impl LogEntry {
+    pub fn new(sequencer_url: String, sequencer_address: Option<String>, identity: String) -> Self {
+        Self {
+            timestamp: Utc::now(),
+            sequencer_url,
+            sequencer_address,
+            identity,
+            entropy_sources: Vec::new(),
+            ceremonies: Vec::new(),
+            ecdsa_signature: None,
+            receipt: None,
+            receipt_verification: None,
+            outcome: Outcome::Pending,
+        }
+    }
+
+    /// Records the public parts of our updated contribution.
+    pub fn record_contribution(&mut self, batch: &BatchContribution) {
+        self.ceremonies = batch
+            .contributions
+            .iter()
+            .map(|contr| SubCeremonyEntry {
+                num_g1_powers: contr.num_g1_powers,
+                num_g2_powers: contr.num_g2_powers,
+                pot_pubkey: contr.pot_pubkey,
+                bls_signature: contr.bls_signature.clone(),
+            })
+            .collect();
+        self.ecdsa_signature = batch.ecdsa_signature.clone();
+    }
+
+    pub fn pot_pubkeys(&self) -> Vec<G2Point> {
+        self.ceremonies.iter().map(|c| c.pot_pubkey).collect()
+    }
+}
+
+/// Directory with one JSON file per contribution attempt.
+pub struct ContributionLog {
+    dir: PathBuf,
+}
+
+impl ContributionLog {
+    pub fn new<P: Into<PathBuf>>(dir: P) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Writes `entry` to a new file of the log and returns its path. Existing entries are never
+    /// overwritten.
+    pub fn append(&self, entry: &LogEntry) -> Result<PathBuf> {
+        fs::create_dir_all(&self.dir)?;
+        let json = serde_json::to_vec_pretty(entry)?;
+
+        let stem = entry.timestamp.format("%Y%m%dT%H%M%S%.3fZ").to_string();
+        for attempt in 0.. {
+            let path = match attempt {
+                0 => self.dir.join(format!("{stem}.json")),
+                n => self.dir.join(format!("{stem}-{n}.json")),
+            };
+
+            match fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&path)
+            {
+                Ok(mut file) => {
+                    file.write_all(&json)?;
+                    file.sync_all()?;
+                    return Ok(path);
+                }
+                Err(err) if err.kind() == io::ErrorKind::AlreadyExists => continue,
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        unreachable!()
+    }
+
+    /// Overwrites the entry at `path`, as returned by [`ContributionLog::append`], with `entry`.
+    pub fn update(&self, path: &Path, entry: &LogEntry) -> Result<()> {
+        let json = serde_json::to_vec_pretty(entry)?;
+
+        // through a rename, so a crash never leaves a torn entry behind
+        let tmp = path.with_extension("json.tmp");
+        let mut file = fs::File::create(&tmp)?;
+        file.write_all(&json)?;
+        file.sync_all()?;
+        fs::rename(&tmp, path)?;
+        Ok(())
+    }
+
+    /// Every entry of the log, oldest first. A log that doesn't exist yet is empty.
+    pub fn entries(&self) -> Result<Vec<LogEntry>> {
+        let dir = match fs::read_dir(&self.dir) {
+            Ok(dir) => dir,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(err) => return Err(err.into()),
+        };
+
+        let mut entries = Vec::new();
+        for file in dir {
+            let path = file?.path();
+            if path.extension().is_some_and(|ext| ext == "json") {
+                entries.push(serde_json::from_slice::<LogEntry>(&fs::read(&path)?)?);
+            }
+        }
+
+        entries.sort_by_key(|entry| entry.timestamp);
+        Ok(entries)
+    }
+
+    /// The most recent successful contribution made as `identity`.
+    pub fn last_contribution(&self, identity: &str) -> Result<Option<LogEntry>> {
+        Ok(self.entries()?.into_iter().rev().find(|entry| {
+            matches!(entry.outcome, Outcome::Contributed)
+                && entry.identity.eq_ignore_ascii_case(identity)
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::contribution::{entropy::Entropy, tests::genesis_batch, update_batch};
+
+    fn temp_log(name: &str) -> ContributionLog {
+        let dir = std::env::temp_dir().join(format!("majlis-kzg-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        ContributionLog::new(dir)
+    }
+
+    #[test]
+    fn append_and_list_entries() {
+        let log = temp_log("history");
+        assert!(log.entries().unwrap().is_empty());
+
+        let mut batch = genesis_batch(&[(8, 3), (16, 3)]);
+        update_batch(&mut batch, "eth|0x01", Entropy::new());
+
+        let mut failed = LogEntry::new("http://localhost/".into(), None, "eth|0x01".into());
+        failed.outcome = Outcome::Failed("rate limited".into());
+        let mut contributed = LogEntry::new("http://localhost/".into(), None, "eth|0x01".into());
+        contributed.record_contribution(&batch);
+        contributed.outcome = Outcome::Contributed;
+
+        let first = log.append(&failed).unwrap();
+        let second = log.append(&contributed).unwrap();
+        assert_ne!(first, second);
+
+        let entries = log.entries().unwrap();
+        assert_eq!(entries.len(), 2);
+        assert!(matches!(entries[0].outcome, Outcome::Failed(_)));
+
+        let last = log.last_contribution("ETH|0x01").unwrap().unwrap();
+        assert_eq!(last.ceremonies[1].num_g1_powers, 16);
+        assert_eq!(
+            last.pot_pubkeys(),
+            vec![
+                batch.contributions[0].pot_pubkey,
+                batch.contributions[1].pot_pubkey
+            ]
+        );
+        assert!(log.last_contribution("eth|0x02").unwrap().is_none());
+
+        fs::remove_dir_all(log.dir()).unwrap();
+    }
+
+    #[test]
+    fn update_pending_entry() {
+        let log = temp_log("history-update");
+
+        let mut entry = LogEntry::new("http://localhost/".into(), None, "eth|0x01".into());
+        let path = log.append(&entry).unwrap();
+        assert!(matches!(
+            log.entries().unwrap()[0].outcome,
+            Outcome::Pending
+        ));
+
+        entry.outcome = Outcome::Contributed;
+        log.update(&path, &entry).unwrap();
+
+        let entries = log.entries().unwrap();
+        assert_eq!(entries.len(), 1);
+        assert!(matches!(entries[0].outcome, Outcome::Contributed));
+        assert_eq!(fs::read_dir(log.dir()).unwrap().count(), 1);
+
+        fs::remove_dir_all(log.dir()).unwrap();
+    }
+}
@@ -2,14 +2,66 @@ use clap::{Parser, Subcommand};
 use color_eyre::{eyre::bail, Result};
 use reqwest::Url;
 use sequencer::{types::TryContributeResponse, SequencerClient, SequencerErrorInner};
-use tokio::time;
+use std::{
+    collections::HashMap,
+    fs,
+    net::{SocketAddr, TcpListener},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+use tokio::time::{self, Duration};
+use zeroize::Zeroizing;
 
 mod contribution;
+mod history;
 mod prompt;
 mod sequencer;
+mod server;
+mod transcript;
 
-use prompt::{do_with_spinner, prompt_authentication, prompt_title};
-use sequencer::{types::TryContributeError, SequencerClientError};
+use contribution::{
+    entropy::Entropy,
+    identity::resolve_identity,
+    secret::disable_core_dumps,
+    subgroup_checks,
+    types::{BatchContribution, BatchTranscript},
+    update_batch,
+    validation::{validate_batch_shape, validate_contribution},
+    verification::verify_update,
+};
+use history::{ContributionLog, LogEntry, Outcome};
+use prompt::{
+    do_with_spinner, download_progress_bar, prompt_admin_token, prompt_authentication,
+    prompt_entropy, prompt_title,
+};
+use sequencer::{
+    types::{Contributor, LobbyState, ReceiptVerification, TryContributeError},
+    SequencerClientError,
+};
+use server::{
+    auth::AuthConfig,
+    http::ServerState,
+    lobby::{Lobby, LobbyConfig},
+    store::Store,
+    Sequencer,
+};
+use transcript::{
+    genesis,
+    inclusion::{find_participant, prove_inclusion, InclusionReport},
+    verify_transcript, SubCeremonyReport,
+};
+
+/// How long to wait between two attempts to leave the lobby.
+const LOBBY_POLL_INTERVAL: Duration = Duration::from_secs(4);
+
+/// Polls of the lobby in a row that may fail for reasons worth retrying, such as a proxy in front
+/// of the sequencer timing out, before giving up.
+const MAX_LOBBY_RETRIES: usize = 5;
+
+/// How many poll intervals to wait after polling the lobby too often. Polls that are rate limited
+/// don't keep our place in the lobby, which the sequencer gives away after a while (a minute for
+/// ours), so the whole budget of retries has to fit in it.
+const RATE_LIMITED_SLOWDOWN: u32 = 2;
 
 #[derive(Parser, Debug)]
 struct App {
@@ -17,6 +69,10 @@ struct App {
     #[arg(default_value = "https://seq.ceremony.ethereum.org")]
     sequencer_url: Url,
 
+    #[arg(long, global = true, help = "Directory of the local contribution log.")]
+    #[arg(default_value = "contributions")]
+    log_dir: PathBuf,
+
     #[command(subcommand)]
     commands: Commands,
 }
@@ -24,20 +80,159 @@ struct App {
 #[derive(Debug, Subcommand)]
 enum Commands {
     #[command(about = "Start the ceremony.")]
-    Start,
+    Start {
+        #[arg(long, help = "Where to save the contribution receipt.")]
+        #[arg(default_value = "contribution_receipt.json")]
+        receipt_path: PathBuf,
+
+        #[arg(long, help = "File whose content is mixed into the secret.")]
+        entropy_file: Option<PathBuf>,
+
+        #[arg(long, help = "Shell command whose output is mixed into the secret.")]
+        entropy_command: Option<String>,
+    },
     #[command(about = "Get ceremony status.")]
     Status,
     #[command(about = "Request the current transcript.")]
-    CurrentState,
+    CurrentState {
+        #[arg(short, long, help = "Where to save the transcript.")]
+        #[arg(default_value = "transcript.json")]
+        output: PathBuf,
+    },
+    #[command(about = "Verify a transcript of the ceremony.")]
+    Verify {
+        #[arg(help = "Path to the transcript.")]
+        transcript: PathBuf,
+    },
+    #[command(about = "Find our contribution in a transcript and prove it is part of the SRS.")]
+    FindMe {
+        #[arg(
+            long,
+            help = "GitHub handle (`@handle`) or Ethereum address we contributed with."
+        )]
+        identity: String,
+
+        #[arg(long, help = "Path to the transcript.")]
+        #[arg(default_value = "transcript.json")]
+        transcript: PathBuf,
+    },
+    #[command(about = "List past contribution attempts from the local log.")]
+    History,
+    #[command(about = "Write the genesis transcript of a new ceremony.")]
+    Init {
+        #[arg(
+            long,
+            help = "G1 and G2 powers of every sub-ceremony, as `g1:g2,g1:g2,...`."
+        )]
+        #[arg(value_delimiter = ',', value_parser = parse_size)]
+        #[arg(default_value = "4096:65,8192:65,16384:65,32768:65")]
+        sizes: Vec<(i32, i32)>,
+
+        #[arg(short, long, help = "Where to save the transcript.")]
+        #[arg(default_value = "transcript.json")]
+        output: PathBuf,
+
+        #[arg(long, help = "Overwrite the output if it exists.")]
+        force: bool,
+    },
+    #[command(about = "Act as the sequencer of a self-hosted ceremony.")]
+    Serve {
+        #[arg(
+            long,
+            help = "Transcript to build on, checkpointed every few contributions and on shutdown."
+        )]
+        #[arg(default_value = "transcript.json")]
+        transcript: PathBuf,
+
+        #[arg(
+            long,
+            help = "JSON file with the tokens handed out to participants and the OAuth2 providers."
+        )]
+        auth: PathBuf,
+
+        #[arg(long, help = "Address to listen on.")]
+        #[arg(default_value = "127.0.0.1:8080")]
+        listen: SocketAddr,
+
+        #[arg(
+            long,
+            help = "URL participants reach the server at, if not http://<listen>/."
+        )]
+        public_url: Option<Url>,
+
+        #[arg(
+            long,
+            help = "Seconds a participant has to contribute once it's their turn."
+        )]
+        #[arg(default_value_t = 180)]
+        contribution_timeout: u64,
+
+        #[cfg(feature = "eth")]
+        #[arg(
+            long,
+            help = "Encrypted JSON keystore of the account that signs receipts."
+        )]
+        keystore: Option<PathBuf>,
+    },
+    #[command(about = "Operate a self-hosted ceremony.")]
+    Admin {
+        #[arg(
+            long,
+            help = "The `admin_token` of the sequencer's auth file. Prompted for if absent."
+        )]
+        token: Option<String>,
+
+        #[command(subcommand)]
+        command: AdminCommands,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum AdminCommands {
+    #[command(about = "Show who is contributing, waiting and banned.")]
+    Lobby,
+    #[command(about = "Stop handing out turns. Whoever is contributing can finish.")]
+    Pause,
+    #[command(about = "Hand out turns again.")]
+    Resume,
+    #[command(about = "Sign a participant out and keep them from signing in again.")]
+    Ban {
+        #[arg(help = "Identity of the participant, e.g. `eth|0x...`.")]
+        identity: String,
+    },
+    #[command(about = "Let a banned participant back in.")]
+    Unban {
+        #[arg(help = "Identity of the participant, e.g. `eth|0x...`.")]
+        identity: String,
+    },
+    #[command(about = "End the turn of whoever is contributing.")]
+    Abort,
+    #[command(about = "Publish the transcript as it stands.")]
+    Snapshot,
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
     let app = App::parse();
     let seq = SequencerClient::new(app.sequencer_url);
+    let log = ContributionLog::new(app.log_dir);
 
     match app.commands {
-        Commands::Start => start_contribution(&seq).await?,
+        Commands::Start {
+            receipt_path,
+            entropy_file,
+            entropy_command,
+        } => {
+            let mut entropy = Entropy::new();
+            if let Some(path) = entropy_file {
+                entropy.add_file(path)?;
+            }
+            if let Some(cmd) = entropy_command {
+                entropy.add_command(cmd)?;
+            }
+
+            start_contribution(&seq, entropy, receipt_path, &log).await?
+        }
 
         Commands::Status => {
             let res = do_with_spinner(seq.status(), "Fetching status")?;
@@ -53,26 +248,338 @@ async fn main() -> Result<()> {
             )
         }
 
-        Commands::CurrentState => {
-            todo!("execute current state command")
+        Commands::CurrentState { output } => {
+            let bar = download_progress_bar("Downloading transcript");
+            let bytes = seq
+                .download_current_state(|downloaded, total| {
+                    if let Some(total) = total {
+                        bar.set_length(total);
+                    }
+                    bar.set_position(downloaded);
+                })
+                .await;
+            bar.finish_and_clear();
+
+            let bytes = bytes?;
+            fs::write(&output, &bytes)?;
+
+            let transcript: BatchTranscript = serde_json::from_slice(&bytes)?;
+            print_transcript_summary(&transcript);
+            println!("\n💾 Transcript saved to {}", output.display());
+        }
+
+        Commands::Verify { transcript } => {
+            let transcript = load_transcript(&transcript)?;
+            let reports = do_with_spinner(
+                async { verify_transcript(&transcript) },
+                "Verifying transcript",
+            );
+
+            print_verification_report(&reports);
+
+            #[cfg(feature = "eth")]
+            {
+                let report = do_with_spinner(
+                    async { transcript::ecdsa::verify_ecdsa_signatures(&transcript) },
+                    "Verifying ECDSA signatures",
+                );
+                print_ecdsa_report(&report);
+                if !report.invalid.is_empty() {
+                    bail!("The transcript has invalid ECDSA signatures.");
+                }
+            }
+
+            if !reports.iter().all(SubCeremonyReport::passed) {
+                bail!("The transcript is invalid.");
+            }
+        }
+
+        Commands::FindMe {
+            identity,
+            transcript,
+        } => {
+            let identity = do_with_spinner(resolve_identity(identity), "Resolving identity")?;
+
+            let Some(entry) = log.last_contribution(&identity)? else {
+                bail!(
+                    "No contribution as {identity} in the log at {}.",
+                    log.dir().display()
+                );
+            };
+            let pubkeys = entry.pot_pubkeys();
+
+            // the receipt is the sequencer's word on which pubkeys it accepted from us
+            if let Some(receipt) = &entry.receipt {
+                if receipt.payload()?.witness != pubkeys {
+                    bail!("The receipt in the log doesn't match our pubkeys.");
+                }
+            }
+
+            let transcript = load_transcript(&transcript)?;
+            let Some(index) = find_participant(&transcript, &identity) else {
+                bail!("{identity} is not in the transcript.");
+            };
+
+            let report = do_with_spinner(
+                async { prove_inclusion(&transcript, index, &pubkeys) },
+                "Checking our contribution",
+            );
+
+            print_inclusion_report(&identity, &report);
+            if !report.proven() {
+                bail!("Our contribution is not part of the transcript.");
+            }
+        }
+
+        Commands::History => print_history(&log.entries()?),
+
+        Commands::Init {
+            sizes,
+            output,
+            force,
+        } => {
+            if output.exists() && !force {
+                bail!(
+                    "{} already exists, pass --force to overwrite it.",
+                    output.display()
+                );
+            }
+
+            let transcript = do_with_spinner(async { genesis(&sizes) }, "Generating transcript");
+            fs::write(&output, serde_json::to_vec(&transcript)?)?;
+            println!(
+                "🌱 Wrote the genesis of {} sub-ceremonies to {}",
+                sizes.len(),
+                output.display()
+            );
+        }
+
+        Commands::Serve {
+            transcript,
+            auth,
+            listen,
+            public_url,
+            contribution_timeout,
+            #[cfg(feature = "eth")]
+            keystore,
+        } => {
+            let store = Store::new(transcript);
+            let transcript = do_with_spinner(async { store.recover() }, "Recovering transcript")?;
+
+            let mut auth: AuthConfig = serde_json::from_slice(&fs::read(auth)?)?;
+            let lobby = Lobby::new(LobbyConfig {
+                contribution_timeout: Duration::from_secs(contribution_timeout),
+                ..Default::default()
+            });
+            let sequencer = Sequencer::new(transcript, HashMap::new(), lobby);
+            #[cfg(feature = "eth")]
+            let sequencer = match keystore {
+                Some(path) => {
+                    let password = prompt::prompt_keystore_password()?;
+                    let wallet = ethers::signers::LocalWallet::decrypt_keystore(path, &*password)?;
+                    let sequencer = sequencer.with_signer(wallet);
+                    println!(
+                        "✍️  Receipts are signed by {}",
+                        sequencer.status().sequencer_address
+                    );
+                    sequencer
+                }
+                None => sequencer,
+            };
+
+            let listener = TcpListener::bind(listen)?;
+            let public_url = match public_url {
+                Some(url) => url,
+                None => Url::parse(&format!("http://{}/", listener.local_addr()?))?,
+            };
+            let state = Arc::new(ServerState {
+                admin_token: auth.admin_token.take(),
+                snapshot: Mutex::new(store.snapshot()?),
+                store: Some(store),
+                auth: auth.into_providers(),
+                ..ServerState::new(sequencer, public_url)
+            });
+
+            println!("🛰️  Serving the ceremony on {}", state.public_url);
+            server::http::serve(listener, state, async {
+                let _ = tokio::signal::ctrl_c().await;
+            })
+            .await?;
+        }
+
+        Commands::Admin { token, command } => {
+            let token = match token {
+                Some(token) => Zeroizing::new(token),
+                None => prompt_admin_token()?,
+            };
+            run_admin(&seq, &token, command).await?
         }
     }
 
     Ok(())
 }
 
-async fn start_contribution(sequencer: &SequencerClient) -> Result<()> {
+async fn run_admin(seq: &SequencerClient, token: &str, command: AdminCommands) -> Result<()> {
+    let admin = seq.admin(token);
+
+    match command {
+        AdminCommands::Lobby => {
+            let lobby = do_with_spinner(admin.lobby(), "Fetching lobby")?;
+            print_lobby_state(&lobby);
+        }
+        AdminCommands::Pause => {
+            do_with_spinner(admin.pause(), "Pausing contributions")?;
+            println!("⏸️  Contributions are paused");
+        }
+        AdminCommands::Resume => {
+            do_with_spinner(admin.resume(), "Resuming contributions")?;
+            println!("▶️  Contributions are resumed");
+        }
+        AdminCommands::Ban { identity } => {
+            do_with_spinner(admin.ban(&identity), "Banning participant")?;
+            println!("🚫 {identity} is banned");
+        }
+        AdminCommands::Unban { identity } => {
+            match do_with_spinner(admin.unban(&identity), "Unbanning participant")? {
+                true => println!("👋 {identity} can take part again"),
+                false => println!("🤷 {identity} wasn't banned"),
+            }
+        }
+        AdminCommands::Abort => {
+            let identity = do_with_spinner(admin.abort(), "Aborting contribution")?;
+            println!("🛑 Ended the turn of {identity}");
+        }
+        AdminCommands::Snapshot => {
+            let snapshot = do_with_spinner(admin.snapshot(), "Publishing snapshot")?;
+            println!(
+                "📸 Published the transcript with {} contribution(s) at {}info/snapshot",
+                snapshot.num_contributions,
+                seq.url()
+            );
+        }
+    }
+
+    Ok(())
+}
+
+async fn start_contribution(
+    sequencer: &SequencerClient,
+    mut entropy: Entropy,
+    receipt_path: PathBuf,
+    log: &ContributionLog,
+) -> Result<()> {
     prompt_title();
-    let session_id = prompt_authentication(sequencer)?;
 
+    let (session_id, identity) = prompt_authentication(sequencer)?;
+    entropy.add_user_input(prompt_entropy()?.as_str());
+
+    run_contribution(
+        sequencer,
+        &session_id,
+        &identity,
+        entropy,
+        receipt_path,
+        log,
+        LOBBY_POLL_INTERVAL,
+    )
+    .await
+}
+
+/// The non-interactive part of `start`: waits for our turn, contributes as the already resolved
+/// `identity`, and logs the attempt.
+async fn run_contribution(
+    sequencer: &SequencerClient,
+    session_id: &str,
+    identity: &str,
+    entropy: Entropy,
+    receipt_path: PathBuf,
+    log: &ContributionLog,
+    lobby_poll_interval: Duration,
+) -> Result<()> {
+    disable_core_dumps()?;
+
+    let sequencer_address = do_with_spinner(sequencer.status(), "Fetching status")
+        .ok()
+        .map(|status| status.sequencer_address);
+    let mut entry = LogEntry::new(
+        sequencer.url().to_string(),
+        sequencer_address,
+        identity.to_string(),
+    );
+    entry.entropy_sources = entropy.sources().iter().map(ToString::to_string).collect();
+
+    // logged as pending first, so an interrupted attempt still leaves a trace
+    let path = match log.append(&entry) {
+        Ok(path) => Some(path),
+        Err(err) => {
+            eprintln!("⚠️  Failed to log the attempt: {err}");
+            None
+        }
+    };
+
+    let res = wait_and_contribute(
+        sequencer,
+        session_id,
+        identity,
+        entropy,
+        receipt_path,
+        &mut entry,
+        lobby_poll_interval,
+    )
+    .await;
+
+    if let (Err(err), Outcome::Pending) = (&res, &entry.outcome) {
+        entry.outcome = Outcome::Failed(err.to_string());
+    }
+    let logged = match path {
+        Some(path) => log.update(&path, &entry).map(|()| path),
+        None => log.append(&entry),
+    };
+    match logged {
+        Ok(path) => println!("\n📒 Attempt logged to {}", path.display()),
+        Err(err) => eprintln!("\n⚠️  Failed to log the attempt: {err}"),
+    }
+
+    res
+}
+
+/// Waits in the lobby until it's our turn, then contributes.
+async fn wait_and_contribute(
+    sequencer: &SequencerClient,
+    session_id: &str,
+    identity: &str,
+    entropy: Entropy,
+    receipt_path: PathBuf,
+    entry: &mut LogEntry,
+    poll_interval: Duration,
+) -> Result<()> {
+    let mut retries = 0;
     loop {
-        let res = do_with_spinner(
-            sequencer.try_contribute(&session_id),
-            " Starting ceremony\n",
-        );
+        let res = do_with_spinner(sequencer.try_contribute(session_id), " Starting ceremony\n");
 
-        if let Err(err) = res {
-            if let SequencerClientError::SequencerError(SequencerErrorInner { code, .. }) = err {
+        let res = match res {
+            Ok(res) => {
+                retries = 0;
+                res
+            }
+            Err(err) if err.is_retryable() && retries < MAX_LOBBY_RETRIES => {
+                // we keep our place in the lobby as long as we poll again soon enough
+                retries += 1;
+                println!("⚠️  {err}. Retrying ({retries}/{MAX_LOBBY_RETRIES})...");
+                time::sleep(poll_interval).await;
+                continue;
+            }
+            Err(SequencerClientError::SequencerError(SequencerErrorInner {
+                code: TryContributeError::RateLimited,
+                ..
+            })) if retries < MAX_LOBBY_RETRIES => {
+                // the sequencer still has us in the lobby, we only polled too soon
+                retries += 1;
+                println!("⚠️  Polling too often. Slowing down ({retries}/{MAX_LOBBY_RETRIES})...");
+                time::sleep(poll_interval * RATE_LIMITED_SLOWDOWN).await;
+                continue;
+            }
+            Err(SequencerClientError::SequencerError(SequencerErrorInner { code, .. })) => {
                 let msg = match code {
                     TryContributeError::UnknownSessionId => {
                         "Invalid session ID. Please try authenticating again."
@@ -81,19 +588,604 @@ async fn start_contribution(sequencer: &SequencerClient) -> Result<()> {
                 };
 
                 bail!(msg)
-            } else {
-                bail!(err);
             }
-        }
+            Err(err) => bail!(err),
+        };
 
-        match res.unwrap() {
+        match res {
             TryContributeResponse::InProgress(msg) => println!("In progress... {msg}"),
 
-            TryContributeResponse::BatchContribution(_) => {
-                todo!("can contribute now");
+            TryContributeResponse::BatchContribution(batch) => {
+                return contribute(
+                    sequencer,
+                    session_id,
+                    identity,
+                    batch,
+                    entropy,
+                    receipt_path,
+                    entry,
+                );
             }
         }
 
-        time::sleep(time::Duration::from_secs(4)).await;
+        time::sleep(poll_interval).await;
+    }
+}
+
+fn contribute(
+    sequencer: &SequencerClient,
+    session_id: &str,
+    identity: &str,
+    mut batch: BatchContribution,
+    entropy: Entropy,
+    receipt_path: PathBuf,
+    entry: &mut LogEntry,
+) -> Result<()> {
+    println!("🎉 It's your turn to contribute!");
+
+    if let Err(err) = validate_batch_shape(&batch) {
+        let _ = do_with_spinner(sequencer.abort_contribution(session_id), "Aborting");
+        bail!("The sequencer sent a malformed contribution ({err}). Your contribution has been aborted.");
+    }
+
+    if let Err(err) = do_with_spinner(async { subgroup_checks(&batch) }, "Checking contribution") {
+        // give up our slot so the lobby can move on to the next participant
+        let _ = do_with_spinner(sequencer.abort_contribution(session_id), "Aborting");
+        bail!("The sequencer sent an invalid contribution ({err}). Your contribution has been aborted.");
+    }
+
+    let sources = entropy
+        .sources()
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>();
+    println!("🎲 Entropy sources: {}", sources.join(", "));
+
+    let previous = batch.clone();
+    do_with_spinner(
+        async { update_batch(&mut batch, identity, entropy) },
+        "Computing contribution",
+    );
+    entry.record_contribution(&batch);
+
+    if let Err(err) = validate_contribution(&previous, &batch) {
+        let _ = do_with_spinner(sequencer.abort_contribution(session_id), "Aborting");
+        bail!("Our contribution would be rejected by the sequencer ({err}). Your contribution has been aborted.");
+    }
+
+    if let Err(err) = do_with_spinner(
+        async { verify_update(&previous, &batch) },
+        "Verifying contribution",
+    ) {
+        let _ = do_with_spinner(sequencer.abort_contribution(session_id), "Aborting");
+        bail!("Our contribution failed verification ({err}). Your contribution has been aborted.");
+    }
+
+    let receipt = match do_with_spinner(
+        sequencer.contribute(&batch, session_id),
+        "Submitting contribution",
+    ) {
+        Ok(receipt) => receipt,
+        Err(SequencerClientError::SequencerError(SequencerErrorInner { code, error })) => {
+            bail!("Contribution rejected ({code}): {error}")
+        }
+        // resubmitting can't help: our turn is over either way
+        Err(err) if err.is_retryable() => bail!(
+            "{err}. The sequencer may still have received our contribution, \
+             check the transcript before trying again."
+        ),
+        Err(err) => bail!(err),
+    };
+
+    // against the address we saw before contributing, so a sequencer can't swap keys on us
+    let verification = match &entry.sequencer_address {
+        Some(address) => receipt.verify(address, identity, &entry.pot_pubkeys()),
+        None => ReceiptVerification::Unverified(
+            "couldn't fetch the sequencer address before contributing".to_string(),
+        ),
+    };
+    // we're in the transcript now, so the log has to say so even if saving the receipt fails
+    let json = serde_json::to_string_pretty(&receipt)?;
+    entry.receipt = Some(receipt);
+    entry.receipt_verification = Some(verification.clone());
+    entry.outcome = Outcome::Contributed;
+
+    fs::write(&receipt_path, &json)?;
+    println!(
+        r#"
+### Contribution receipt ###
+{json}
+
+🧾 Receipt saved to {}"#,
+        receipt_path.display()
+    );
+    match &verification {
+        ReceiptVerification::Verified => println!("🔏 Receipt verified: signed by the sequencer"),
+        ReceiptVerification::Unverified(reason) => println!("⚠️  Receipt unverified: {reason}"),
+    }
+
+    Ok(())
+}
+
+fn print_lobby_state(lobby: &LobbyState) {
+    println!("\n### Lobby ###");
+    if lobby.paused {
+        println!("⏸️  Contributions are paused");
+    }
+
+    match &lobby.contributing {
+        Some(Contributor {
+            identity,
+            seconds_left: Some(seconds_left),
+        }) => println!("🧮 Contributing: {identity} ({seconds_left}s left)"),
+        Some(Contributor { identity, .. }) => println!("🔍 Verifying: {identity}"),
+        None => println!("🪑 Nobody is contributing"),
+    }
+
+    println!("🎪 Waiting: {}", lobby.waiting.len());
+    for (i, identity) in lobby.waiting.iter().enumerate() {
+        println!("  {}. {identity}", i + 1);
+    }
+
+    println!("🚫 Banned: {}", lobby.banned.len());
+    for identity in &lobby.banned {
+        println!("  - {identity}");
+    }
+}
+
+fn print_transcript_summary(transcript: &BatchTranscript) {
+    println!(
+        r#"
+### Current state ###
+👥 No. of participants: {}"#,
+        transcript.participant_ids.len().saturating_sub(1)
+    );
+
+    for (i, transcript) in transcript.transcripts.iter().enumerate() {
+        // the first running product is the generator the ceremony started from
+        let participants = transcript.witness.running_products.len().saturating_sub(1);
+        let last_product = transcript
+            .witness
+            .running_products
+            .last()
+            .map(ToString::to_string)
+            .unwrap_or_else(|| "-".to_string());
+
+        println!(
+            r#"
+Transcript #{i}
+  🔢 G1 powers: {}
+  🔢 G2 powers: {}
+  👥 Participants: {participants}
+  🔗 Last running product: {last_product}"#,
+            transcript.num_g1_powers, transcript.num_g2_powers,
+        );
+    }
+}
+
+/// A sub-ceremony size of `init`, as `num_g1_powers:num_g2_powers`.
+fn parse_size(size: &str) -> std::result::Result<(i32, i32), String> {
+    let Some((num_g1, num_g2)) = size.split_once(':') else {
+        return Err("expected `g1:g2`".to_string());
+    };
+    // transcripts count powers with an `i32`
+    let num_g1 = num_g1.parse::<i32>().map_err(|err| err.to_string())?;
+    let num_g2 = num_g2.parse::<i32>().map_err(|err| err.to_string())?;
+
+    // the checks every transcript goes through
+    if num_g2 < 2 || num_g2 > num_g1 {
+        return Err("expected at least 2 G2 powers, and no more G2 than G1 powers".to_string());
+    }
+
+    Ok((num_g1, num_g2))
+}
+
+fn load_transcript(path: &Path) -> Result<BatchTranscript> {
+    let bytes = fs::read(path)?;
+    let transcript = do_with_spinner(
+        async { serde_json::from_slice(&bytes) },
+        "Loading transcript",
+    )?;
+    Ok(transcript)
+}
+
+fn print_verification_report(reports: &[SubCeremonyReport]) {
+    println!("\n### Transcript verification ###");
+
+    for report in reports {
+        let status = if report.passed() { "PASS" } else { "FAIL" };
+        println!(
+            r#"
+Transcript #{} [{status}]
+  🔢 G1 powers: {}
+  🔢 G2 powers: {}
+  👥 Participants: {}"#,
+            report.index, report.num_g1_powers, report.num_g2_powers, report.num_participants
+        );
+
+        for (check, result) in report.checks.iter() {
+            match result {
+                Ok(()) => println!("  ✅ {check}"),
+                Err(failure) => println!("  ❌ {check}: {failure}"),
+            }
+        }
+
+        if let Some(signatures) = &report.signatures {
+            if signatures.invalid.is_empty() {
+                println!("  ✅ BLS signatures");
+            } else {
+                println!("  ❌ Invalid BLS signatures: {}", signatures.invalid.len());
+                for participant in signatures.invalid.iter() {
+                    println!("     - {participant}");
+                }
+            }
+
+            if !signatures.missing.is_empty() {
+                println!("  ⚠️  Missing BLS signatures: {}", signatures.missing.len());
+                for participant in signatures.missing.iter() {
+                    println!("     - {participant}");
+                }
+            }
+        }
+    }
+}
+
+fn print_inclusion_report(identity: &str, report: &InclusionReport) {
+    println!(
+        r#"
+### Our contribution ###
+🪪 Identity: {identity}
+🔢 Index: {}"#,
+        report.index
+    );
+
+    for (i, ceremony) in report.ceremonies.iter().enumerate() {
+        let mark = |ok: bool| if ok { "✅" } else { "❌" };
+        println!(
+            r#"
+Transcript #{i}
+  {} Pubkey matches our contribution log
+  {} Linked to the previous running product
+  {} Linked to the next running product"#,
+            mark(ceremony.pubkey_matches),
+            mark(ceremony.linked_to_previous),
+            mark(ceremony.linked_to_next)
+        );
+    }
+
+    if report.proven() {
+        println!("\n🎉 Our secret is part of the final SRS.");
+    }
+}
+
+#[cfg(feature = "eth")]
+fn print_ecdsa_report(report: &transcript::ecdsa::EcdsaReport) {
+    println!("\n### ECDSA signatures ###\n");
+    println!("  ✅ Verified Ethereum accounts: {}", report.verified.len());
+
+    if !report.invalid.is_empty() {
+        println!("  ❌ Invalid signatures: {}", report.invalid.len());
+        for participant in report.invalid.iter() {
+            println!("     - {participant}");
+        }
+    }
+
+    if !report.missing.is_empty() {
+        println!("  ⚠️  Missing signatures: {}", report.missing.len());
+        for participant in report.missing.iter() {
+            println!("     - {participant}");
+        }
+    }
+}
+
+fn print_history(entries: &[LogEntry]) {
+    if entries.is_empty() {
+        println!("No contribution attempts yet.");
+        return;
+    }
+
+    println!("\n### Contribution history ###");
+    for entry in entries {
+        let outcome = match &entry.outcome {
+            Outcome::Pending => "⏳ Pending".to_string(),
+            Outcome::Contributed => "✅ Contributed".to_string(),
+            Outcome::Failed(err) => format!("❌ Failed: {err}"),
+        };
+        let sizes = entry
+            .ceremonies
+            .iter()
+            .map(|c| format!("{}/{}", c.num_g1_powers, c.num_g2_powers))
+            .collect::<Vec<_>>();
+
+        println!(
+            r#"
+🕒 {}
+  🪪 Identity: {}
+  💻 Sequencer: {}
+  🔢 Sub-ceremonies (G1/G2 powers): {}
+  🧾 Receipt: {}
+  {outcome}"#,
+            entry.timestamp.to_rfc3339(),
+            entry.identity,
+            entry.sequencer_url,
+            if sizes.is_empty() {
+                "-".to_string()
+            } else {
+                sizes.join(", ")
+            },
+            match (&entry.receipt, &entry.receipt_verification) {
+                (None, _) => "none".to_string(),
+                (Some(_), Some(ReceiptVerification::Verified)) => "verified".to_string(),
+                (Some(_), Some(ReceiptVerification::Unverified(reason))) => {
+                    format!("unverified ({reason})")
+                }
+                (Some(_), None) => "unverified".to_string(),
+            },
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{
+        extract::State,
+        http::{Request, StatusCode},
+        middleware::{self, Next},
+        response::{IntoResponse, Response},
+    };
+    use contribution::point::G1Point;
+    use server::http::{router, tests::TestServer};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use transcript::tests::transcript_with;
+
+    const ALICE: &str = "eth|0x0000000000000000000000000000000000000001";
+
+    /// A proxy in front of the sequencer, which counts the polls of the lobby and answers the
+    /// first `outages` of them with its own error page.
+    #[derive(Clone)]
+    struct Proxy {
+        polls: Arc<AtomicUsize>,
+        outages: usize,
+    }
+
+    async fn proxy<B>(State(proxy): State<Proxy>, req: Request<B>, next: Next<B>) -> Response {
+        if req.uri().path() == "/lobby/try_contribute"
+            && proxy.polls.fetch_add(1, Ordering::SeqCst) < proxy.outages
+        {
+            return (StatusCode::BAD_GATEWAY, "<html>502 Bad Gateway</html>").into_response();
+        }
+        next.run(req).await
+    }
+
+    /// A self-hosted ceremony over `transcript`, in which `alice` and `bob` are signed in.
+    struct Ceremony {
+        server: TestServer,
+        proxy: Proxy,
+    }
+
+    impl Ceremony {
+        fn start(transcript: BatchTranscript, lobby: LobbyConfig, outages: usize) -> Self {
+            let sessions = HashMap::from([
+                ("alice".to_string(), ALICE.to_string()),
+                (
+                    "bob".to_string(),
+                    "eth|0x0000000000000000000000000000000000000002".to_string(),
+                ),
+            ]);
+            let sequencer = Sequencer::new(transcript, sessions, Lobby::new(lobby));
+            let proxy = Proxy {
+                polls: Arc::new(AtomicUsize::new(0)),
+                outages,
+            };
+
+            let server = TestServer::spawn(|url| {
+                router(Arc::new(ServerState::new(sequencer, url.clone())))
+                    .layer(middleware::from_fn_with_state(proxy.clone(), self::proxy))
+            });
+            Self { server, proxy }
+        }
+
+        fn lobby_polls(&self) -> usize {
+            self.proxy.polls.load(Ordering::SeqCst)
+        }
+    }
+
+    /// A lobby that lets the client poll as often as it likes.
+    fn lobby() -> LobbyConfig {
+        LobbyConfig {
+            min_poll_interval: Duration::ZERO,
+            ..Default::default()
+        }
+    }
+
+    /// Runs the whole `start` flow as alice and returns its result, along with the log.
+    fn run(ceremony: &Ceremony, name: &str) -> (Result<()>, ContributionLog, PathBuf) {
+        run_polling_every(ceremony, name, Duration::from_millis(10))
+    }
+
+    fn run_polling_every(
+        ceremony: &Ceremony,
+        name: &str,
+        poll_interval: Duration,
+    ) -> (Result<()>, ContributionLog, PathBuf) {
+        let dir = std::env::temp_dir().join(format!("majlis-kzg-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let receipt_path = dir.join("receipt.json");
+        let log = ContributionLog::new(dir.join("log"));
+
+        let res = async_std::task::block_on(run_contribution(
+            &ceremony.server.client(),
+            "alice",
+            ALICE,
+            Entropy::new(),
+            receipt_path.clone(),
+            &log,
+            poll_interval,
+        ));
+
+        (res, log, receipt_path)
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn contribute_after_waiting_in_the_lobby() {
+        let ceremony = Ceremony::start(transcript_with(&[]).0, lobby(), 0);
+        let client = ceremony.server.client();
+        client.try_contribute("bob").await.unwrap();
+        // once alice has been kept waiting
+        let polls = ceremony.proxy.polls.clone();
+        let bob_gives_up = tokio::spawn(async move {
+            while polls.load(Ordering::SeqCst) < 3 {
+                time::sleep(Duration::from_millis(5)).await;
+            }
+            client.abort_contribution("bob").await.unwrap();
+        });
+
+        let (res, log, receipt_path) = run(&ceremony, "e2e-contribute");
+        res.unwrap();
+        bob_gives_up.await.unwrap();
+        assert!(ceremony.lobby_polls() > 3);
+        assert!(receipt_path.exists());
+
+        let transcript = ceremony.server.client().current_state().await.unwrap();
+        assert_eq!(transcript.participant_ids.len(), 2);
+
+        let entries = log.entries().unwrap();
+        assert_eq!(entries.len(), 1);
+        assert!(matches!(entries[0].outcome, Outcome::Contributed));
+        assert_eq!(
+            entries[0].pot_pubkeys(),
+            transcript
+                .transcripts
+                .iter()
+                .map(|transcript| *transcript.witness.pot_pubkeys.last().unwrap())
+                .collect::<Vec<_>>()
+        );
+        assert!(entries[0].receipt.is_some());
+
+        fs::remove_dir_all(log.dir().parent().unwrap()).unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn abort_invalid_batch_from_sequencer() {
+        let (mut transcript, _) = transcript_with(&[]);
+        // on the curve, but outside the prime-order subgroup
+        transcript.transcripts[1].powers_of_tau.g1_powers[3] = format!("0x80{}04", "00".repeat(46))
+            .parse::<G1Point>()
+            .unwrap();
+
+        let ceremony = Ceremony::start(transcript, lobby(), 0);
+        let (res, log, receipt_path) = run(&ceremony, "e2e-abort");
+        assert!(res
+            .unwrap_err()
+            .to_string()
+            .contains("invalid contribution"));
+        assert!(!receipt_path.exists());
+
+        // the turn was given back
+        let client = ceremony.server.client();
+        assert!(matches!(
+            client.try_contribute("bob").await,
+            Ok(TryContributeResponse::BatchContribution(_))
+        ));
+        assert_eq!(
+            client.current_state().await.unwrap().participant_ids.len(),
+            1
+        );
+
+        let entries = log.entries().unwrap();
+        assert!(matches!(entries[0].outcome, Outcome::Failed(_)));
+        assert!(entries[0].ceremonies.is_empty());
+
+        fs::remove_dir_all(log.dir().parent().unwrap()).unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn abort_malformed_batch_from_sequencer() {
+        let (mut transcript, _) = transcript_with(&[]);
+        transcript.transcripts[0].num_g1_powers = 64;
+
+        let ceremony = Ceremony::start(transcript, lobby(), 0);
+        let (res, log, _) = run(&ceremony, "e2e-malformed");
+        assert!(res
+            .unwrap_err()
+            .to_string()
+            .contains("malformed contribution"));
+
+        // the turn was given back
+        assert!(matches!(
+            ceremony.server.client().try_contribute("bob").await,
+            Ok(TryContributeResponse::BatchContribution(_))
+        ));
+
+        fs::remove_dir_all(log.dir().parent().unwrap()).unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn report_rejected_contribution() {
+        // the turn is over before the contribution arrives
+        let ceremony = Ceremony::start(
+            transcript_with(&[]).0,
+            LobbyConfig {
+                contribution_timeout: Duration::ZERO,
+                ..lobby()
+            },
+            0,
+        );
+        let (res, log, _) = run(&ceremony, "e2e-rejected");
+        assert!(res
+            .unwrap_err()
+            .to_string()
+            .contains("ContributeError::NotUsersTurn"));
+
+        let entries = log.entries().unwrap();
+        assert!(matches!(entries[0].outcome, Outcome::Failed(_)));
+        assert_eq!(entries[0].ceremonies.len(), 2);
+        assert!(entries[0].receipt.is_none());
+
+        fs::remove_dir_all(log.dir().parent().unwrap()).unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn retry_lobby_outages() {
+        let ceremony = Ceremony::start(transcript_with(&[]).0, lobby(), MAX_LOBBY_RETRIES);
+        let (res, log, _) = run(&ceremony, "e2e-outages");
+        res.unwrap();
+        assert_eq!(ceremony.lobby_polls(), MAX_LOBBY_RETRIES + 1);
+        fs::remove_dir_all(log.dir().parent().unwrap()).unwrap();
+
+        let ceremony = Ceremony::start(transcript_with(&[]).0, lobby(), MAX_LOBBY_RETRIES + 1);
+        let (res, log, _) = run(&ceremony, "e2e-outage");
+        assert!(res.unwrap_err().to_string().contains("502 Bad Gateway"));
+        assert_eq!(ceremony.lobby_polls(), MAX_LOBBY_RETRIES + 1);
+        fs::remove_dir_all(log.dir().parent().unwrap()).unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn back_off_when_rate_limited() {
+        let ceremony = Ceremony::start(
+            transcript_with(&[]).0,
+            LobbyConfig {
+                min_poll_interval: Duration::from_secs(3600),
+                // scaled down along with the poll interval below
+                poll_timeout: Duration::from_secs(3),
+                // had she lost her place, alice would contribute after bob instead of giving up
+                contribution_timeout: Duration::from_secs(10),
+            },
+            0,
+        );
+        // bob keeps alice waiting after her first poll, and she may not poll again
+        let client = ceremony.server.client();
+        client.try_contribute("bob").await.unwrap();
+
+        let (res, log, _) =
+            run_polling_every(&ceremony, "e2e-rate-limited", Duration::from_millis(100));
+        assert!(res.unwrap_err().to_string().contains("too many requests"));
+        assert_eq!(ceremony.lobby_polls(), 1 + 1 + MAX_LOBBY_RETRIES + 1);
+        // slowing down didn't cost alice her place
+        assert_eq!(client.status().await.unwrap().lobby_size, 1);
+
+        fs::remove_dir_all(log.dir().parent().unwrap()).unwrap();
     }
 }
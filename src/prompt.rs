@@ -1,19 +1,13 @@
 use color_eyre::Result;
-use indicatif::{ProgressBar, ProgressFinish};
-use inquire::{Select, Text};
+use indicatif::{ProgressBar, ProgressStyle};
+use inquire::{Password, PasswordDisplayMode, Select, Text};
 use std::{borrow::Cow, future::Future, time::Duration};
+use zeroize::Zeroizing;
 
-use crate::sequencer::SequencerClient;
-
-#[cfg(not(feature = "eth"))]
-const NO_AUTH_PROVIDERS: usize = 1;
-#[cfg(feature = "eth")]
-const NO_AUTH_PROVIDERS: usize = 2;
-const AUTH_PROVIDERS: [&str; NO_AUTH_PROVIDERS] = [
-    "GitHub",
-    #[cfg(feature = "eth")]
-    "Ethereum Address",
-];
+use crate::{
+    contribution::identity::resolve_identity,
+    sequencer::{types::AuthMethod, SequencerClient},
+};
 
 pub fn do_with_spinner<F, T>(future: F, message: impl Into<Cow<'static, str>>) -> T
 where
@@ -26,33 +20,103 @@ where
     res
 }
 
-pub fn prompt_authentication(sequencer: &SequencerClient) -> Result<String> {
-    let res = Select::new(
-        "Select how you want to authenticate yourself.",
-        AUTH_PROVIDERS.into(),
+pub fn download_progress_bar(message: impl Into<Cow<'static, str>>) -> ProgressBar {
+    let style = ProgressStyle::with_template(
+        "{spinner} {msg} [{bar:40}] {bytes}/{total_bytes} ({bytes_per_sec}, {eta})",
     )
-    .prompt()
-    .unwrap();
-
-    let auth_links = do_with_spinner(sequencer.request_auth_link(), "")?;
-    let link = match res {
-        "GitHub" => auth_links.github_auth_url,
-        #[cfg(feature = "eth")]
-        "Ethereum Address" => auth_links.eth_auth_url,
-        _ => unreachable!("unsupported auth provider"),
+    .unwrap()
+    .progress_chars("=> ");
+
+    let bar = ProgressBar::new(0).with_style(style).with_message(message);
+    bar.enable_steady_tick(Duration::from_millis(100));
+    bar
+}
+
+/// Returns the session ID along with the identity the user authenticated with, as the sequencer
+/// expects it to be signed.
+pub fn prompt_authentication(sequencer: &SequencerClient) -> Result<(String, String)> {
+    let listed = do_with_spinner(sequencer.auth_providers(), "")?;
+    let legacy = listed.is_none();
+    let providers = match listed {
+        Some(providers) => providers,
+        None => do_with_spinner(sequencer.request_auth_link(), "")?.providers(),
+    };
+    let provider =
+        Select::new("Select how you want to authenticate yourself.", providers).prompt()?;
+
+    let url = match provider.method {
+        AuthMethod::Browser { url } => url,
+        AuthMethod::Token => {
+            let token = Password::new("Enter the token you were given : ")
+                .without_confirmation()
+                .with_display_mode(PasswordDisplayMode::Masked)
+                .prompt()
+                .map(Zeroizing::new)?;
+            let session = do_with_spinner(sequencer.login(&provider.id, token.as_str()), "")?;
+
+            println!();
+            return Ok((session.session_id, session.identity));
+        }
     };
 
     println!(
         r#"
 Click the link below to authenticate and obtain your session ID :
 
-{link}
+{url}
 "#,
     );
 
     let session_id = Text::new("Enter your session ID : ").prompt()?;
-    println!("");
-    Ok(session_id)
+    let identity = if legacy {
+        let message = match provider.id.as_str() {
+            "eth" => "Enter your Ethereum address : ",
+            _ => "Enter your GitHub handle (eg. @kariy) : ",
+        };
+        let handle = Text::new(message).prompt()?;
+        do_with_spinner(resolve_identity(handle), "Resolving identity")?
+    } else {
+        // the sequencer knows who the session belongs to, whatever it calls its providers
+        do_with_spinner(sequencer.session(&session_id), "")?.identity
+    };
+
+    println!();
+    Ok((session_id, identity))
+}
+
+/// Optional text that is mixed into the secret along with the other entropy sources.
+pub fn prompt_entropy() -> Result<Zeroizing<String>> {
+    let input = Password::new("Type some random text to add to your secret (optional) : ")
+        .without_confirmation()
+        .with_display_mode(PasswordDisplayMode::Masked)
+        .prompt()
+        .map(Zeroizing::new)?;
+
+    println!();
+    Ok(input)
+}
+
+/// Password of the keystore that holds the sequencer's key.
+#[cfg(feature = "eth")]
+pub fn prompt_keystore_password() -> Result<Zeroizing<String>> {
+    let input = Password::new("Enter the keystore password : ")
+        .without_confirmation()
+        .with_display_mode(PasswordDisplayMode::Masked)
+        .prompt()
+        .map(Zeroizing::new)?;
+
+    Ok(input)
+}
+
+/// The `admin_token` of the sequencer's auth file.
+pub fn prompt_admin_token() -> Result<Zeroizing<String>> {
+    let input = Password::new("Enter the admin token : ")
+        .without_confirmation()
+        .with_display_mode(PasswordDisplayMode::Masked)
+        .prompt()
+        .map(Zeroizing::new)?;
+
+    Ok(input)
 }
 
 pub fn prompt_title() {
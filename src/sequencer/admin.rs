@@ -0,0 +1,108 @@
+//! The `admin/` endpoints of a self-hosted sequencer, for its operator.
+
+use reqwest::{Client, RequestBuilder, Response, StatusCode};
+use serde::de::DeserializeOwned;
+
+use super::{
+    json, sequencer_error,
+    types::{AdminError, IdentityRequest, LobbyState, SnapshotResponse},
+    unexpected_status, Result, SequencerClient,
+};
+
+pub struct AdminClient<'a> {
+    sequencer: &'a SequencerClient,
+    /// Borrowed rather than copied, so that it's only ever held where the caller wipes it.
+    token: &'a str,
+}
+
+impl SequencerClient {
+    /// Acts as the operator, with the `admin_token` of the sequencer's auth file.
+    pub fn admin<'a>(&'a self, token: &'a str) -> AdminClient<'a> {
+        AdminClient {
+            sequencer: self,
+            token,
+        }
+    }
+}
+
+impl AdminClient<'_> {
+    pub async fn lobby(&self) -> Result<LobbyState, AdminError> {
+        let res = self
+            ._authenticated(Client::new().get(self.url("lobby")))
+            .send()
+            .await?;
+        parse(res).await
+    }
+
+    /// Stops handing out turns. Whoever is contributing can finish.
+    pub async fn pause(&self) -> Result<(), AdminError> {
+        self.post("pause").await
+    }
+
+    pub async fn resume(&self) -> Result<(), AdminError> {
+        self.post("resume").await
+    }
+
+    /// Signs the participant out and keeps them from signing in again.
+    pub async fn ban(&self, identity: &str) -> Result<(), AdminError> {
+        self.post_identity("ban", identity).await
+    }
+
+    /// Lets a banned participant back in. Returns whether they were banned.
+    pub async fn unban(&self, identity: &str) -> Result<bool, AdminError> {
+        self.post_identity("unban", identity).await
+    }
+
+    /// Ends the turn of whoever is contributing, and returns their identity.
+    pub async fn abort(&self) -> Result<String, AdminError> {
+        let res = self
+            ._authenticated(Client::new().post(self.url("abort")))
+            .send()
+            .await?;
+        Ok(parse::<IdentityRequest>(res).await?.identity)
+    }
+
+    /// Publishes the transcript as it stands at `info/snapshot`.
+    pub async fn snapshot(&self) -> Result<SnapshotResponse, AdminError> {
+        self.post("snapshot").await
+    }
+
+    async fn post<T: DeserializeOwned>(&self, path: &str) -> Result<T, AdminError> {
+        let res = self
+            ._authenticated(Client::new().post(self.url(path)))
+            .send()
+            .await?;
+        parse(res).await
+    }
+
+    async fn post_identity<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        identity: &str,
+    ) -> Result<T, AdminError> {
+        let res = self
+            ._authenticated(Client::new().post(self.url(path)))
+            .json(&IdentityRequest {
+                identity: identity.to_string(),
+            })
+            .send()
+            .await?;
+        parse(res).await
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}admin/{path}", self.sequencer.url())
+    }
+
+    fn _authenticated(&self, request: RequestBuilder) -> RequestBuilder {
+        request.bearer_auth(self.token)
+    }
+}
+
+async fn parse<T: DeserializeOwned>(res: Response) -> Result<T, AdminError> {
+    match res.status() {
+        StatusCode::OK => json(res).await,
+        StatusCode::BAD_REQUEST | StatusCode::UNAUTHORIZED => sequencer_error(res).await,
+        _ => unexpected_status(res).await,
+    }
+}
@@ -1,22 +1,43 @@
-use reqwest::{get, Client, RequestBuilder, StatusCode};
-use serde::{Deserialize, Serialize};
+use reqwest::{get, Client, RequestBuilder, Response, StatusCode};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use url::Url;
 
+pub mod admin;
 pub mod types;
 
-use crate::contribution::types::{BatchContribution, BatchTranscript};
-use types::{AuthResponse, CeremonyStatus, ContributionReceipt, TryContributeResponse};
-use types::{ContributionAbortError, ContributionError, TryContributeError};
+use crate::contribution::types::BatchContribution;
+#[cfg(test)]
+use crate::contribution::types::BatchTranscript;
+use types::{AuthError, ContributionAbortError, ContributionError, SessionError};
+use types::{AuthProviderInfo, AuthResponse, CeremonyStatus, ContributionReceipt};
+use types::{SessionResponse, TryContributeError, TryContributeResponse};
 
 #[derive(Debug, thiserror::Error)]
 pub enum SequencerClientError<E> {
-    #[allow(unused)]
     #[error(transparent)]
     JsonError(serde_json::Error),
     #[error(transparent)]
     TransportError(reqwest::Error),
     #[error(transparent)]
     SequencerError(SequencerErrorInner<E>),
+    /// A response the API doesn't define, such as an error page of a proxy in front of the
+    /// sequencer.
+    #[error("Unexpected response from the sequencer: {status} {body}")]
+    UnexpectedStatus { status: StatusCode, body: String },
+}
+
+impl<E> SequencerClientError<E> {
+    /// Whether the same request may succeed later: the sequencer couldn't be reached, or it or a
+    /// proxy in front of it is overloaded or down.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Self::TransportError(err) => err.is_connect() || err.is_timeout(),
+            Self::UnexpectedStatus { status, .. } => {
+                status.is_server_error() || *status == StatusCode::TOO_MANY_REQUESTS
+            }
+            Self::JsonError(_) | Self::SequencerError(_) => false,
+        }
+    }
 }
 
 #[cfg_attr(test, derive(PartialEq, Eq))]
@@ -29,6 +50,9 @@ pub struct SequencerErrorInner<E> {
 
 type Result<T, E> = std::result::Result<T, SequencerClientError<E>>;
 
+/// Bytes of an unexpected response body kept for diagnosis.
+const MAX_BODY_LEN: usize = 1024;
+
 pub struct SequencerClient {
     url: Url,
 }
@@ -38,21 +62,48 @@ impl SequencerClient {
         Self { url }
     }
 
+    pub fn url(&self) -> &Url {
+        &self.url
+    }
+
     pub async fn status(&self) -> Result<CeremonyStatus, String> {
         let res = get(format!("{}info/status", self.url)).await?;
 
         match res.status() {
-            StatusCode::OK => Ok(res.json().await?),
-            _ => unreachable!("unknown response code"),
+            StatusCode::OK => json(res).await,
+            _ => unexpected_status(res).await,
         }
     }
 
+    /// The current transcript, parsed. The CLI saves the raw bytes instead (see
+    /// [`download_current_state`](Self::download_current_state)).
+    #[cfg(test)]
     pub async fn current_state(&self) -> Result<BatchTranscript, String> {
-        let res = get(format!("{}info/current_state", self.url)).await?;
+        let bytes = self.download_current_state(|_, _| {}).await?;
+        serde_json::from_slice(&bytes).map_err(SequencerClientError::JsonError)
+    }
+
+    /// Downloads the raw current transcript, calling `on_progress` with the number of bytes
+    /// received so far and the total size (if the sequencer reports it) after every chunk.
+    pub async fn download_current_state<F>(&self, mut on_progress: F) -> Result<Vec<u8>, String>
+    where
+        F: FnMut(u64, Option<u64>),
+    {
+        let mut res = get(format!("{}info/current_state", self.url)).await?;
 
         match res.status() {
-            StatusCode::OK => Ok(res.json().await?),
-            _ => unreachable!("unknown response code"),
+            StatusCode::OK => {
+                let total = res.content_length();
+                let mut bytes = Vec::with_capacity(total.unwrap_or_default() as usize);
+
+                while let Some(chunk) = res.chunk().await? {
+                    bytes.extend_from_slice(&chunk);
+                    on_progress(bytes.len() as u64, total);
+                }
+
+                Ok(bytes)
+            }
+            _ => unexpected_status(res).await,
         }
     }
 
@@ -60,8 +111,60 @@ impl SequencerClient {
         let res = get(format!("{}auth/request_link", self.url)).await?;
 
         match res.status() {
-            StatusCode::OK => Ok(res.json().await?),
-            _ => unreachable!("unknown response code"),
+            StatusCode::OK => json(res).await,
+            _ => unexpected_status(res).await,
+        }
+    }
+
+    /// The ways to sign in, or `None` for sequencers that don't list them and only offer the links
+    /// of [`request_auth_link`](Self::request_auth_link).
+    pub async fn auth_providers(&self) -> Result<Option<Vec<AuthProviderInfo>>, String> {
+        let res = get(format!("{}auth/providers", self.url)).await?;
+
+        match res.status() {
+            StatusCode::OK => json(res).await.map(Some),
+            StatusCode::NOT_FOUND => Ok(None),
+            _ => unexpected_status(res).await,
+        }
+    }
+
+    /// Trades a token handed out by the operator for a session.
+    pub async fn login<T: AsRef<str>>(
+        &self,
+        provider: &str,
+        token: T,
+    ) -> Result<SessionResponse, AuthError> {
+        let res = Client::new()
+            .post(format!("{}auth/{provider}/login", self.url))
+            .json(&serde_json::json!({ "token": token.as_ref() }))
+            .send()
+            .await?;
+
+        match res.status() {
+            StatusCode::OK => json(res).await,
+            StatusCode::BAD_REQUEST
+            | StatusCode::UNAUTHORIZED
+            | StatusCode::FORBIDDEN
+            | StatusCode::NOT_FOUND => sequencer_error(res).await,
+            _ => unexpected_status(res).await,
+        }
+    }
+
+    /// The identity `session_id` was minted for.
+    pub async fn session<T: AsRef<str>>(
+        &self,
+        session_id: T,
+    ) -> Result<SessionResponse, SessionError> {
+        let res = Client::new()
+            .get(format!("{}auth/session", self.url))
+            .bearer_auth(session_id.as_ref())
+            .send()
+            .await?;
+
+        match res.status() {
+            StatusCode::OK => json(res).await,
+            StatusCode::UNAUTHORIZED => sequencer_error(res).await,
+            _ => unexpected_status(res).await,
         }
     }
 
@@ -75,12 +178,9 @@ impl SequencerClient {
             .await?;
 
         match res.status() {
-            StatusCode::OK => Ok(res.json().await?),
-            StatusCode::BAD_REQUEST | StatusCode::UNAUTHORIZED => {
-                let err = res.json().await?;
-                Err(SequencerClientError::SequencerError(err))
-            }
-            _ => unreachable!("unknown response code"),
+            StatusCode::OK => json(res).await,
+            StatusCode::BAD_REQUEST | StatusCode::UNAUTHORIZED => sequencer_error(res).await,
+            _ => unexpected_status(res).await,
         }
     }
 
@@ -96,12 +196,9 @@ impl SequencerClient {
             .await?;
 
         match res.status() {
-            StatusCode::OK => Ok(res.json().await?),
-            StatusCode::BAD_REQUEST => {
-                let err = res.json().await?;
-                Err(SequencerClientError::SequencerError(err))
-            }
-            _ => unreachable!("unknown response code"),
+            StatusCode::OK => json(res).await,
+            StatusCode::BAD_REQUEST => sequencer_error(res).await,
+            _ => unexpected_status(res).await,
         }
     }
 
@@ -115,12 +212,9 @@ impl SequencerClient {
             .await?;
 
         match res.status() {
-            StatusCode::OK => Ok(res.json().await?),
-            StatusCode::BAD_REQUEST => {
-                let err = res.json().await?;
-                Err(SequencerClientError::SequencerError(err))
-            }
-            _ => unreachable!("unknown response code"),
+            StatusCode::OK => json(res).await,
+            StatusCode::BAD_REQUEST => sequencer_error(res).await,
+            _ => unexpected_status(res).await,
         }
     }
 
@@ -135,6 +229,34 @@ impl SequencerClient {
     }
 }
 
+/// Parses a response body, so that a malformed one is a `JsonError` rather than a transport error.
+async fn json<T: DeserializeOwned, E>(res: Response) -> Result<T, E> {
+    let bytes = res.bytes().await?;
+    serde_json::from_slice(&bytes).map_err(SequencerClientError::JsonError)
+}
+
+/// The error code and message the sequencer answered with.
+async fn sequencer_error<T, E: DeserializeOwned>(res: Response) -> Result<T, E> {
+    Err(SequencerClientError::SequencerError(
+        json::<_, E>(res).await?,
+    ))
+}
+
+async fn unexpected_status<T, E>(res: Response) -> Result<T, E> {
+    let status = res.status();
+    let mut body = res.text().await.unwrap_or_default();
+    if body.len() > MAX_BODY_LEN {
+        let end = (0..=MAX_BODY_LEN)
+            .rev()
+            .find(|&end| body.is_char_boundary(end))
+            .unwrap_or_default();
+        body.truncate(end);
+        body.push('…');
+    }
+
+    Err(SequencerClientError::UnexpectedStatus { status, body })
+}
+
 impl<E> From<reqwest::Error> for SequencerClientError<E> {
     fn from(value: reqwest::Error) -> Self {
         Self::TransportError(value)
@@ -162,4 +284,69 @@ mod tests {
 
         assert_eq!(err, expected);
     }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn report_unexpected_responses() {
+        use crate::server::http::tests::TestServer;
+        use axum::{
+            routing::{get, post},
+            Router,
+        };
+
+        // a proxy in front of a sequencer that's down
+        let proxy = TestServer::spawn(|_| {
+            Router::new()
+                .route(
+                    "/lobby/try_contribute",
+                    post(|| async { (StatusCode::BAD_GATEWAY, "<html>502 Bad Gateway</html>") }),
+                )
+                .route("/info/current_state", get(|| async { "<html>" }))
+        });
+        let client = proxy.client();
+
+        let err = client.try_contribute("alice").await.err().unwrap();
+        assert!(err.is_retryable());
+        let SequencerClientError::UnexpectedStatus { status, body } = err else {
+            panic!("a proxy error page isn't an error of the sequencer");
+        };
+        assert_eq!(status, StatusCode::BAD_GATEWAY);
+        assert!(body.contains("Bad Gateway"));
+
+        let err = client.current_state().await.unwrap_err();
+        assert!(matches!(err, SequencerClientError::JsonError(_)));
+        assert!(!err.is_retryable());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn discover_providers_from_auth_links() {
+        use super::types::AuthMethod;
+        use crate::server::http::tests::TestServer;
+        use axum::{routing::get, Json, Router};
+
+        // a sequencer that predates `auth/providers`
+        let legacy = TestServer::spawn(|_| {
+            Router::new().route(
+                "/auth/request_link",
+                get(|| async {
+                    Json(AuthResponse {
+                        eth_auth_url: "http://localhost/auth/eth".to_string(),
+                        github_auth_url: "http://localhost/auth/github".to_string(),
+                    })
+                }),
+            )
+        });
+        let client = legacy.client();
+        assert!(client.auth_providers().await.unwrap().is_none());
+
+        let providers = client.request_auth_link().await.unwrap().providers();
+
+        assert_eq!(providers[0].id, "github");
+        assert_eq!(
+            providers[0].method,
+            AuthMethod::Browser {
+                url: "http://localhost/auth/github".to_string()
+            }
+        );
+        assert_eq!(providers.len(), if cfg!(feature = "eth") { 2 } else { 1 });
+    }
 }
@@ -1,7 +1,14 @@
+#[cfg(feature = "eth")]
+use ethers::{
+    core::types::{Address, Signature},
+    signers::LocalWallet,
+    utils::hash_message,
+};
 use serde::{Deserialize, Serialize};
+use std::fmt;
 use thiserror::Error;
 
-use crate::contribution::types::BatchContribution;
+use crate::contribution::{point::G2Point, types::BatchContribution};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CeremonyStatus {
@@ -16,6 +23,87 @@ pub struct AuthResponse {
     pub github_auth_url: String,
 }
 
+impl AuthResponse {
+    /// The sign-in links as providers, for sequencers that don't list theirs.
+    pub fn providers(self) -> Vec<AuthProviderInfo> {
+        let links = [
+            ("github", "GitHub", self.github_auth_url),
+            #[cfg(feature = "eth")]
+            ("eth", "Ethereum Address", self.eth_auth_url),
+        ];
+
+        links
+            .into_iter()
+            .filter(|(_, _, url)| !url.is_empty())
+            .map(|(id, name, url)| AuthProviderInfo {
+                id: id.to_string(),
+                name: name.to_string(),
+                method: AuthMethod::Browser { url },
+            })
+            .collect()
+    }
+}
+
+/// A way to sign in to the sequencer.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuthProviderInfo {
+    pub id: String,
+    pub name: String,
+    #[serde(flatten)]
+    pub method: AuthMethod,
+}
+
+impl fmt::Display for AuthProviderInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.name)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", tag = "method")]
+pub enum AuthMethod {
+    /// The participant signs in at `url` and is shown their session ID.
+    Browser { url: String },
+    /// The participant trades a token they were given for a session.
+    Token,
+}
+
+/// What the operator of a self-hosted sequencer sees of the lobby.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LobbyState {
+    pub paused: bool,
+    pub contributing: Option<Contributor>,
+    /// Identities of the participants waiting for their turn, in order.
+    pub waiting: Vec<String>,
+    pub banned: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Contributor {
+    pub identity: String,
+    /// Time left to contribute, or `None` once the contribution is being verified.
+    pub seconds_left: Option<u64>,
+}
+
+/// Identity an operator bans or lets back in.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct IdentityRequest {
+    pub identity: String,
+}
+
+/// A transcript published by the operator of a self-hosted sequencer.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SnapshotResponse {
+    pub num_contributions: i32,
+}
+
+/// A session and the identity it belongs to.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SessionResponse {
+    pub session_id: String,
+    pub identity: String,
+}
+
 #[derive(Serialize, Deserialize)]
 #[serde(untagged)]
 pub enum TryContributeResponse {
@@ -29,6 +117,118 @@ pub struct ContributionReceipt {
     signature: String,
 }
 
+impl ContributionReceipt {
+    pub fn new(receipt: String, signature: String) -> Self {
+        Self { receipt, signature }
+    }
+
+    /// Signs `payload` with the sequencer's account, in the way [`ContributionReceipt::verify`]
+    /// checks it.
+    #[cfg(feature = "eth")]
+    pub fn sign(payload: &ReceiptPayload, wallet: &LocalWallet) -> Self {
+        let receipt = payload.to_canonical_json();
+        let signature = wallet.sign_hash(hash_message(&receipt));
+
+        Self {
+            receipt,
+            signature: format!("0x{signature}"),
+        }
+    }
+
+    pub fn payload(&self) -> serde_json::Result<ReceiptPayload> {
+        serde_json::from_str(&self.receipt)
+    }
+
+    /// Checks that the receipt is about our contribution (`identity` and `pubkeys`), and that it
+    /// was signed by `sequencer_address`.
+    pub fn verify(
+        &self,
+        sequencer_address: &str,
+        identity: &str,
+        pubkeys: &[G2Point],
+    ) -> ReceiptVerification {
+        let payload = match self.payload() {
+            Ok(payload) => payload,
+            Err(err) => {
+                return ReceiptVerification::Unverified(format!("malformed receipt: {err}"))
+            }
+        };
+
+        if !payload.identity.eq_ignore_ascii_case(identity) {
+            return ReceiptVerification::Unverified(format!(
+                "the receipt is for {}",
+                payload.identity
+            ));
+        }
+        if payload.witness != pubkeys {
+            return ReceiptVerification::Unverified(
+                "the receipt lists other pubkeys than ours".to_string(),
+            );
+        }
+
+        self.verify_signature(sequencer_address)
+    }
+
+    /// The sequencer signs the receipt as an Ethereum message (EIP-191) with its account.
+    #[cfg(feature = "eth")]
+    fn verify_signature(&self, sequencer_address: &str) -> ReceiptVerification {
+        let Ok(expected) = sequencer_address.parse::<Address>() else {
+            return ReceiptVerification::Unverified(format!(
+                "invalid sequencer address {sequencer_address}"
+            ));
+        };
+
+        let signer = self
+            .signature
+            .parse::<Signature>()
+            .map_err(|err| err.to_string())
+            .and_then(|signature| {
+                signature
+                    .recover(self.receipt.as_str())
+                    .map_err(|err| err.to_string())
+            });
+
+        match signer {
+            Ok(signer) if signer == expected => ReceiptVerification::Verified,
+            Ok(signer) => ReceiptVerification::Unverified(format!(
+                "signed by {signer:?} instead of the sequencer"
+            )),
+            Err(err) => ReceiptVerification::Unverified(format!("invalid signature: {err}")),
+        }
+    }
+
+    #[cfg(not(feature = "eth"))]
+    fn verify_signature(&self, _sequencer_address: &str) -> ReceiptVerification {
+        ReceiptVerification::Unverified(
+            "signatures can only be checked with the `eth` feature".to_string(),
+        )
+    }
+}
+
+/// Whether a [`ContributionReceipt`] proves that the sequencer accepted our contribution.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", tag = "status", content = "reason")]
+pub enum ReceiptVerification {
+    Verified,
+    Unverified(String),
+}
+
+/// What the sequencer signs in a [`ContributionReceipt`]: who contributed and the `pot_pubkey`
+/// of each sub-ceremony.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ReceiptPayload {
+    pub identity: String,
+    pub witness: Vec<G2Point>,
+}
+
+impl ReceiptPayload {
+    /// The text that is signed: compact JSON with `identity` first, then `witness` in the order of
+    /// the sub-ceremonies.
+    pub fn to_canonical_json(&self) -> String {
+        serde_json::to_string(self).expect("a receipt payload is always valid JSON")
+    }
+}
+
 #[cfg_attr(test, derive(PartialEq, Eq))]
 #[derive(Debug, Serialize, Deserialize, Error)]
 pub enum SessionError {
@@ -37,6 +237,37 @@ pub enum SessionError {
     InvalidSessionId,
 }
 
+#[cfg_attr(test, derive(PartialEq, Eq))]
+#[derive(Debug, Serialize, Deserialize, Error)]
+pub enum AuthError {
+    #[error("AuthError::UnknownProvider")]
+    #[serde(rename = "AuthError::UnknownProvider")]
+    UnknownProvider,
+    #[error("AuthError::InvalidCredentials")]
+    #[serde(rename = "AuthError::InvalidCredentials")]
+    InvalidCredentials,
+    #[error("AuthError::ProviderUnavailable")]
+    #[serde(rename = "AuthError::ProviderUnavailable")]
+    ProviderUnavailable,
+    #[error("AuthError::Banned")]
+    #[serde(rename = "AuthError::Banned")]
+    Banned,
+}
+
+#[cfg_attr(test, derive(PartialEq, Eq))]
+#[derive(Debug, Serialize, Deserialize, Error)]
+pub enum AdminError {
+    #[error("AdminError::InvalidToken")]
+    #[serde(rename = "AdminError::InvalidToken")]
+    InvalidToken,
+    #[error("AdminError::NobodyContributing")]
+    #[serde(rename = "AdminError::NobodyContributing")]
+    NobodyContributing,
+    #[error("AdminError::NoSnapshot")]
+    #[serde(rename = "AdminError::NoSnapshot")]
+    NoSnapshot,
+}
+
 #[cfg_attr(test, derive(PartialEq, Eq))]
 #[derive(Debug, Serialize, Deserialize, Error)]
 pub enum TryContributeError {
@@ -54,6 +285,9 @@ pub enum ContributeError {
     #[error("ContributeError::NotUsersTurn")]
     #[serde(rename = "ContributeError::NotUsersTurn")]
     NotUsersTurn,
+    #[error("ContributeError::AlreadySubmitted")]
+    #[serde(rename = "ContributeError::AlreadySubmitted")]
+    AlreadySubmitted,
 }
 
 #[cfg_attr(test, derive(PartialEq, Eq))]
@@ -160,6 +394,8 @@ pub enum ContributionError {
     Ceremony(CeremonyError),
     #[error(transparent)]
     Ceremonies(CeremoniesError),
+    #[error(transparent)]
+    Contribute(ContributeError),
 }
 
 #[cfg_attr(test, derive(PartialEq, Eq))]
@@ -193,4 +429,56 @@ mod tests {
             ContributionAbortError::Session(SessionError::InvalidSessionId)
         );
     }
+
+    #[test]
+    fn parse_receipt_payload() {
+        let pubkey = G2Point::generator().to_string();
+        let receipt: ContributionReceipt = serde_json::from_value(serde_json::json!({
+            "receipt": format!(r#"{{"identity":"eth|0x01","witness":["{pubkey}"]}}"#),
+            "signature": "0x",
+        }))
+        .unwrap();
+
+        let payload = receipt.payload().unwrap();
+        assert_eq!(payload.identity, "eth|0x01");
+        assert_eq!(payload.witness, vec![G2Point::generator()]);
+        assert_eq!(payload.to_canonical_json(), receipt.receipt);
+    }
+
+    #[cfg(feature = "eth")]
+    #[test]
+    fn verify_receipt_signature() {
+        use ethers::{
+            signers::{LocalWallet, Signer},
+            utils::hash_message,
+        };
+
+        let sequencer = "4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318"
+            .parse::<LocalWallet>()
+            .unwrap();
+        let address = format!("{:?}", sequencer.address());
+        let pubkeys = [G2Point::generator()];
+
+        let sign = |receipt: String, wallet: &LocalWallet| ContributionReceipt {
+            signature: format!("0x{}", wallet.sign_hash(hash_message(&receipt))),
+            receipt,
+        };
+        let receipt = format!(r#"{{"identity":"eth|0x01","witness":["{}"]}}"#, pubkeys[0]);
+
+        let signed = sign(receipt.clone(), &sequencer);
+        assert_eq!(
+            signed.verify(&address, "eth|0x01", &pubkeys),
+            ReceiptVerification::Verified
+        );
+        assert!(matches!(
+            signed.verify(&address, "eth|0x02", &pubkeys),
+            ReceiptVerification::Unverified(_)
+        ));
+
+        let forged = sign(receipt, &LocalWallet::new(&mut rand_core::OsRng));
+        assert!(matches!(
+            forged.verify(&address, "eth|0x01", &pubkeys),
+            ReceiptVerification::Unverified(_)
+        ));
+    }
 }
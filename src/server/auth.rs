@@ -0,0 +1,403 @@
+//! How participants of a self-hosted ceremony prove who they are.
+//!
+//! A provider turns credentials into an identity, for which the sequencer then mints a session.
+//! Providers either take the credentials in a `auth/<id>/login` request, or send the participant's
+//! browser elsewhere to sign in and get them back through `auth/<id>/callback`.
+
+use rand_core::{OsRng, RngCore};
+use reqwest::{Client, StatusCode};
+use serde::Deserialize;
+use serde_json::Value;
+use std::{
+    collections::HashMap,
+    future::Future,
+    pin::Pin,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+use url::Url;
+
+use crate::sequencer::types::AuthError;
+
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+pub trait AuthProvider: Send + Sync {
+    /// Name of the provider in URLs.
+    fn id(&self) -> &str;
+
+    /// Name of the provider shown to participants.
+    fn name(&self) -> &str;
+
+    /// Whether participants sign in with their browser, starting at [`AuthProvider::redirect`].
+    fn uses_browser(&self) -> bool {
+        false
+    }
+
+    /// Where to send the participant's browser to sign in.
+    fn redirect(&self) -> Option<Url> {
+        None
+    }
+
+    /// The identity of whoever presents `credentials`: the body of a login request, or the query
+    /// of a callback.
+    fn authenticate<'a>(
+        &'a self,
+        credentials: &'a HashMap<String, String>,
+    ) -> BoxFuture<'a, Result<String, AuthError>>;
+}
+
+/// Providers of the `--auth` file of `serve`.
+#[derive(Default, Deserialize)]
+pub struct AuthConfig {
+    /// Identity of the participant each token was handed out to.
+    #[serde(default)]
+    pub tokens: HashMap<String, String>,
+    #[serde(default)]
+    pub oauth2: Vec<OAuth2Config>,
+    /// Bearer token of the `admin/` endpoints, which are disabled without one.
+    pub admin_token: Option<String>,
+}
+
+impl AuthConfig {
+    pub fn into_providers(self) -> Vec<Box<dyn AuthProvider>> {
+        let mut providers = Vec::<Box<dyn AuthProvider>>::new();
+        if !self.tokens.is_empty() {
+            providers.push(Box::new(TokenProvider::new(self.tokens)));
+        }
+        for config in self.oauth2 {
+            providers.push(Box::new(OAuth2Provider::new(config)));
+        }
+
+        providers
+    }
+}
+
+/// An allowlist of participants, each with a token handed out by the operator.
+pub struct TokenProvider {
+    tokens: HashMap<String, String>,
+}
+
+impl TokenProvider {
+    pub fn new(tokens: HashMap<String, String>) -> Self {
+        Self { tokens }
+    }
+}
+
+impl AuthProvider for TokenProvider {
+    fn id(&self) -> &str {
+        "token"
+    }
+
+    fn name(&self) -> &str {
+        "Token"
+    }
+
+    fn authenticate<'a>(
+        &'a self,
+        credentials: &'a HashMap<String, String>,
+    ) -> BoxFuture<'a, Result<String, AuthError>> {
+        let identity = credentials
+            .get("token")
+            .and_then(|token| self.tokens.get(token))
+            .cloned()
+            .ok_or(AuthError::InvalidCredentials);
+
+        Box::pin(async move { identity })
+    }
+}
+
+#[derive(Deserialize)]
+pub struct OAuth2Config {
+    pub id: String,
+    pub name: String,
+    pub authorize_url: Url,
+    pub token_url: Url,
+    /// Endpoint that describes the user an access token belongs to.
+    pub userinfo_url: Url,
+    pub client_id: String,
+    pub client_secret: String,
+    /// The `auth/<id>/callback` endpoint of the sequencer, as registered with the provider.
+    pub redirect_url: Url,
+    /// Field of the user info that identifies the participant.
+    #[serde(default = "default_id_field")]
+    pub id_field: String,
+    /// Prepended to the value of `id_field` to make the identity, e.g. `git|`.
+    pub identity_prefix: String,
+}
+
+fn default_id_field() -> String {
+    "sub".to_string()
+}
+
+/// How long a participant has to sign in with an OAuth2 provider.
+const SIGN_IN_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+
+/// Sign-ins that can be in progress at once. Past that, the oldest ones are dropped, so sign-ins
+/// that are started but never finished can't grow without bound.
+const MAX_PENDING_SIGN_INS: usize = 10_000;
+
+/// Any provider of the OAuth2 authorization code flow.
+pub struct OAuth2Provider {
+    config: OAuth2Config,
+    /// `state` of the sign-ins that haven't come back yet, and when they were started.
+    pending: Mutex<HashMap<String, Instant>>,
+}
+
+impl OAuth2Provider {
+    pub fn new(config: OAuth2Config) -> Self {
+        Self {
+            config,
+            pending: Mutex::new(HashMap::new()),
+        }
+    }
+
+    async fn exchange(&self, code: &str) -> Result<String, AuthError> {
+        let client = Client::new();
+        let res = client
+            .post(self.config.token_url.clone())
+            .header("Accept", "application/json")
+            .form(&[
+                ("grant_type", "authorization_code"),
+                ("code", code),
+                ("redirect_uri", self.config.redirect_url.as_str()),
+                ("client_id", &self.config.client_id),
+                ("client_secret", &self.config.client_secret),
+            ])
+            .send()
+            .await
+            .map_err(|_| AuthError::ProviderUnavailable)?;
+        let token = json_response(res).await?["access_token"]
+            .as_str()
+            .map(ToString::to_string)
+            .ok_or(AuthError::InvalidCredentials)?;
+
+        let res = client
+            .get(self.config.userinfo_url.clone())
+            .header("Accept", "application/json")
+            .header("User-Agent", "majlis-kzg")
+            .bearer_auth(token)
+            .send()
+            .await
+            .map_err(|_| AuthError::ProviderUnavailable)?;
+        let user = json_response(res).await?;
+
+        match &user[&self.config.id_field] {
+            Value::String(id) => Ok(format!("{}{id}", self.config.identity_prefix)),
+            Value::Number(id) => Ok(format!("{}{id}", self.config.identity_prefix)),
+            _ => Err(AuthError::ProviderUnavailable),
+        }
+    }
+}
+
+impl AuthProvider for OAuth2Provider {
+    fn id(&self) -> &str {
+        &self.config.id
+    }
+
+    fn name(&self) -> &str {
+        &self.config.name
+    }
+
+    fn uses_browser(&self) -> bool {
+        true
+    }
+
+    fn redirect(&self) -> Option<Url> {
+        let state = random_hex();
+        let now = Instant::now();
+
+        let mut pending = self.pending.lock().unwrap();
+        pending.retain(|_, started| now.duration_since(*started) < SIGN_IN_TIMEOUT);
+        if pending.len() >= MAX_PENDING_SIGN_INS {
+            let oldest = pending
+                .iter()
+                .min_by_key(|(_, started)| **started)
+                .map(|(state, _)| state.clone());
+            if let Some(oldest) = oldest {
+                pending.remove(&oldest);
+            }
+        }
+        pending.insert(state.clone(), now);
+
+        let mut url = self.config.authorize_url.clone();
+        url.query_pairs_mut()
+            .append_pair("response_type", "code")
+            .append_pair("client_id", &self.config.client_id)
+            .append_pair("redirect_uri", self.config.redirect_url.as_str())
+            .append_pair("state", &state);
+        Some(url)
+    }
+
+    fn authenticate<'a>(
+        &'a self,
+        credentials: &'a HashMap<String, String>,
+    ) -> BoxFuture<'a, Result<String, AuthError>> {
+        Box::pin(async move {
+            // each sign-in can only come back once, and only in time
+            let known_state = credentials
+                .get("state")
+                .and_then(|state| self.pending.lock().unwrap().remove(state))
+                .is_some_and(|started| started.elapsed() < SIGN_IN_TIMEOUT);
+            let code = credentials.get("code");
+
+            match (known_state, code) {
+                (true, Some(code)) => self.exchange(code).await,
+                _ => Err(AuthError::InvalidCredentials),
+            }
+        })
+    }
+}
+
+async fn json_response(res: reqwest::Response) -> Result<Value, AuthError> {
+    match res.status() {
+        StatusCode::OK => res.json().await.map_err(|_| AuthError::ProviderUnavailable),
+        status if status.is_client_error() => Err(AuthError::InvalidCredentials),
+        _ => Err(AuthError::ProviderUnavailable),
+    }
+}
+
+/// 128 random bits, for session IDs and the like.
+pub fn random_hex() -> String {
+    let mut bytes = [0u8; 16];
+    OsRng.fill_bytes(&mut bytes);
+    hex::encode(bytes)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::server::http::tests::TestServer;
+    use axum::{
+        extract::Query,
+        http::{header::AUTHORIZATION, HeaderMap},
+        response::{IntoResponse, Redirect, Response},
+        routing::{get, post},
+        Form, Json, Router,
+    };
+    use serde_json::json;
+
+    /// Sign-ins at the stand-in always succeed, as the user with ID 42.
+    pub(crate) fn stand_in() -> TestServer {
+        async fn authorize(Query(query): Query<HashMap<String, String>>) -> Response {
+            let mut url = Url::parse(&query["redirect_uri"]).unwrap();
+            url.query_pairs_mut()
+                .append_pair("code", "good-code")
+                .append_pair("state", &query["state"]);
+            Redirect::to(url.as_str()).into_response()
+        }
+
+        async fn token(Form(form): Form<HashMap<String, String>>) -> Response {
+            match form["code"].as_str() {
+                "good-code" => Json(json!({ "access_token": "good-token" })).into_response(),
+                _ => StatusCode::BAD_REQUEST.into_response(),
+            }
+        }
+
+        async fn userinfo(headers: HeaderMap) -> Response {
+            match headers[AUTHORIZATION].to_str().unwrap() {
+                "Bearer good-token" => Json(json!({ "sub": 42 })).into_response(),
+                _ => StatusCode::UNAUTHORIZED.into_response(),
+            }
+        }
+
+        TestServer::spawn(|_| {
+            Router::new()
+                .route("/authorize", get(authorize))
+                .route("/token", post(token))
+                .route("/userinfo", get(userinfo))
+        })
+    }
+
+    pub(crate) fn oauth2_config(stand_in: &Url, sequencer: &Url) -> OAuth2Config {
+        OAuth2Config {
+            id: "oauth".to_string(),
+            name: "Stand-in".to_string(),
+            authorize_url: stand_in.join("authorize").unwrap(),
+            token_url: stand_in.join("token").unwrap(),
+            userinfo_url: stand_in.join("userinfo").unwrap(),
+            client_id: "client".to_string(),
+            client_secret: "secret".to_string(),
+            redirect_url: sequencer.join("auth/oauth/callback").unwrap(),
+            id_field: default_id_field(),
+            identity_prefix: "oauth|".to_string(),
+        }
+    }
+
+    fn credentials(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    fn state_of(redirect: &Url) -> String {
+        redirect
+            .query_pairs()
+            .find(|(key, _)| key == "state")
+            .map(|(_, state)| state.to_string())
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn authenticate_with_token() {
+        let provider = TokenProvider::new(credentials(&[("secret", "eth|0x01")]));
+
+        assert_eq!(
+            provider
+                .authenticate(&credentials(&[("token", "secret")]))
+                .await,
+            Ok("eth|0x01".to_string())
+        );
+        assert_eq!(
+            provider
+                .authenticate(&credentials(&[("token", "guess")]))
+                .await,
+            Err(AuthError::InvalidCredentials)
+        );
+        assert!(provider.redirect().is_none());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn authenticate_with_oauth2() {
+        let stand_in = stand_in();
+        let sequencer = Url::parse("http://localhost/").unwrap();
+        let provider = OAuth2Provider::new(oauth2_config(&stand_in.url, &sequencer));
+
+        let state = state_of(&provider.redirect().unwrap());
+        let callback = credentials(&[("code", "good-code"), ("state", &state)]);
+        assert_eq!(
+            provider.authenticate(&callback).await,
+            Ok("oauth|42".to_string())
+        );
+        // the same sign-in can't be replayed
+        assert_eq!(
+            provider.authenticate(&callback).await,
+            Err(AuthError::InvalidCredentials)
+        );
+
+        let state = state_of(&provider.redirect().unwrap());
+        let callback = credentials(&[("code", "bad-code"), ("state", &state)]);
+        assert_eq!(
+            provider.authenticate(&callback).await,
+            Err(AuthError::InvalidCredentials)
+        );
+    }
+
+    #[tokio::test]
+    async fn drop_oldest_pending_sign_ins() {
+        let sequencer = Url::parse("http://localhost/").unwrap();
+        let provider = OAuth2Provider::new(oauth2_config(&sequencer, &sequencer));
+
+        let first = state_of(&provider.redirect().unwrap());
+        for _ in 0..MAX_PENDING_SIGN_INS {
+            provider.redirect().unwrap();
+        }
+        assert_eq!(provider.pending.lock().unwrap().len(), MAX_PENDING_SIGN_INS);
+
+        let callback = credentials(&[("code", "good-code"), ("state", &first)]);
+        assert_eq!(
+            provider.authenticate(&callback).await,
+            Err(AuthError::InvalidCredentials)
+        );
+    }
+}
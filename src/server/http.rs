@@ -0,0 +1,869 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::{header::AUTHORIZATION, HeaderMap, Request, StatusCode},
+    middleware::{self, Next},
+    response::{IntoResponse, Redirect, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use color_eyre::Result;
+use serde::Serialize;
+use serde_json::json;
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    future::Future,
+    net::TcpListener,
+    sync::{Arc, Mutex},
+};
+use subtle::ConstantTimeEq;
+use url::Url;
+
+use super::{
+    auth::AuthProvider,
+    store::{JournalEntry, Store},
+    verify_contribution, Sequencer,
+};
+use crate::{
+    contribution::types::{BatchContribution, BatchTranscript},
+    sequencer::types::{
+        AdminError, AuthError, AuthMethod, AuthProviderInfo, AuthResponse, ContributionError,
+        IdentityRequest, SessionError, SessionResponse, SnapshotResponse, TryContributeError,
+    },
+};
+
+/// Contributions journaled between two checkpoints of the transcript. The journal is replayed
+/// and verified again on recovery, so it shouldn't grow too long either.
+const CHECKPOINT_INTERVAL: usize = 8;
+
+/// What the request handlers share.
+pub struct ServerState {
+    pub sequencer: Mutex<Sequencer>,
+    /// Where accepted contributions are saved, if anywhere.
+    pub store: Option<Store>,
+    /// Held while a contribution is accepted, journaled and applied, or while the transcript is
+    /// checkpointed, so these happen one at a time without holding `sequencer` during the I/O.
+    pub(crate) commit: tokio::sync::Mutex<()>,
+    pub auth: Vec<Box<dyn AuthProvider>>,
+    /// Where participants reach the server, for the links it hands out.
+    pub public_url: Url,
+    /// Bearer token of the `admin/` endpoints, which are disabled without one.
+    pub admin_token: Option<String>,
+    /// The serialized transcript the operator published last.
+    pub snapshot: Mutex<Option<Vec<u8>>>,
+}
+
+impl ServerState {
+    /// A server without storage, sign-ins or operator.
+    pub fn new(sequencer: Sequencer, public_url: Url) -> Self {
+        Self {
+            sequencer: Mutex::new(sequencer),
+            store: None,
+            commit: tokio::sync::Mutex::new(()),
+            auth: Vec::new(),
+            public_url,
+            admin_token: None,
+            snapshot: Mutex::new(None),
+        }
+    }
+
+    fn auth_provider(&self, id: &str) -> Option<&dyn AuthProvider> {
+        self.auth
+            .iter()
+            .find(|provider| provider.id() == id)
+            .map(AsRef::as_ref)
+    }
+}
+
+type Shared = Arc<ServerState>;
+
+/// The REST API that [`SequencerClient`](crate::sequencer::SequencerClient) talks to.
+pub fn router(state: Shared) -> Router {
+    let admin = Router::new()
+        .route("/lobby", get(admin_lobby))
+        .route("/pause", post(admin_pause))
+        .route("/resume", post(admin_resume))
+        .route("/ban", post(admin_ban))
+        .route("/unban", post(admin_unban))
+        .route("/abort", post(admin_abort))
+        .route("/snapshot", post(admin_snapshot))
+        .route_layer(middleware::from_fn_with_state(state.clone(), require_admin));
+
+    Router::new()
+        .route("/info/status", get(status))
+        .route("/info/current_state", get(current_state))
+        .route("/info/snapshot", get(snapshot))
+        .route("/auth/request_link", get(request_link))
+        .route("/auth/providers", get(auth_providers))
+        .route("/auth/session", get(session))
+        .route("/auth/:provider/login", post(login))
+        .route("/auth/:provider/authorize", get(authorize))
+        .route("/auth/:provider/callback", get(callback))
+        .route("/lobby/try_contribute", post(try_contribute))
+        .route("/contribute", post(contribute))
+        .route("/contribution/abort", post(abort))
+        .nest("/admin", admin)
+        .with_state(state)
+}
+
+/// Serves the API on `listener` until `shutdown` completes, and then checkpoints the transcript.
+pub async fn serve<F>(listener: TcpListener, state: Shared, shutdown: F) -> Result<()>
+where
+    F: Future<Output = ()>,
+{
+    axum::Server::from_tcp(listener)?
+        .serve(router(state.clone()).into_make_service())
+        .with_graceful_shutdown(shutdown)
+        .await?;
+
+    checkpoint(&state).await
+}
+
+/// Replaces the checkpoint with the transcript as it stands, which empties the journal.
+async fn checkpoint(state: &Shared) -> Result<()> {
+    // nothing can be journaled in the meantime that the checkpoint would miss
+    let _commit = state.commit.lock().await;
+    let transcript = state.sequencer.lock().unwrap().transcript().clone();
+
+    let state = state.clone();
+    tokio::task::spawn_blocking(move || match &state.store {
+        Some(store) => store.checkpoint(&transcript),
+        None => Ok(()),
+    })
+    .await?
+}
+
+/// Makes `entry` durable, if there is anywhere to save it.
+async fn journal(state: &Shared, entry: JournalEntry) -> Result<JournalEntry> {
+    let state = state.clone();
+    tokio::task::spawn_blocking(move || {
+        if let Some(store) = &state.store {
+            store.append(&entry)?;
+        }
+        Ok(entry)
+    })
+    .await?
+}
+
+/// Serializes `transcript` and saves it as the published snapshot, if there is anywhere to save
+/// it.
+async fn write_snapshot(state: &Shared, transcript: BatchTranscript) -> Result<Vec<u8>> {
+    let state = state.clone();
+    tokio::task::spawn_blocking(move || {
+        let bytes = serde_json::to_vec(&transcript)?;
+        if let Some(store) = &state.store {
+            store.write_snapshot(&bytes)?;
+        }
+        Ok(bytes)
+    })
+    .await?
+}
+
+fn error<E: Serialize + Display>(status: StatusCode, code: E) -> Response {
+    let body = json!({
+        "code": code,
+        "error": code.to_string(),
+    });
+    (status, Json(body)).into_response()
+}
+
+fn session_id(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(AUTHORIZATION)?
+        .to_str()
+        .ok()?
+        .strip_prefix("Bearer ")
+}
+
+async fn status(State(state): State<Shared>) -> Response {
+    Json(state.sequencer.lock().unwrap().status()).into_response()
+}
+
+async fn current_state(State(state): State<Shared>) -> Response {
+    Json(state.sequencer.lock().unwrap().transcript()).into_response()
+}
+
+async fn snapshot(State(state): State<Shared>) -> Response {
+    match state.snapshot.lock().unwrap().clone() {
+        Some(bytes) => ([("content-type", "application/json")], bytes).into_response(),
+        None => error(StatusCode::NOT_FOUND, AdminError::NoSnapshot),
+    }
+}
+
+async fn request_link() -> Response {
+    // sessions are handed out by the operator
+    Json(AuthResponse {
+        eth_auth_url: String::new(),
+        github_auth_url: String::new(),
+    })
+    .into_response()
+}
+
+async fn auth_providers(State(state): State<Shared>) -> Response {
+    let providers = state
+        .auth
+        .iter()
+        .map(|provider| AuthProviderInfo {
+            id: provider.id().to_string(),
+            name: provider.name().to_string(),
+            method: match provider.uses_browser() {
+                true => AuthMethod::Browser {
+                    url: format!("{}auth/{}/authorize", state.public_url, provider.id()),
+                },
+                false => AuthMethod::Token,
+            },
+        })
+        .collect::<Vec<_>>();
+
+    Json(providers).into_response()
+}
+
+async fn session(State(state): State<Shared>, headers: HeaderMap) -> Response {
+    let Some(session_id) = session_id(&headers) else {
+        return error(StatusCode::UNAUTHORIZED, SessionError::InvalidSessionId);
+    };
+
+    match state.sequencer.lock().unwrap().identity(session_id) {
+        Some(identity) => Json(SessionResponse {
+            session_id: session_id.to_string(),
+            identity: identity.to_string(),
+        })
+        .into_response(),
+        None => error(StatusCode::UNAUTHORIZED, SessionError::InvalidSessionId),
+    }
+}
+
+async fn login(
+    State(state): State<Shared>,
+    Path(provider): Path<String>,
+    Json(credentials): Json<HashMap<String, String>>,
+) -> Response {
+    sign_in(&state, &provider, credentials).await
+}
+
+async fn authorize(State(state): State<Shared>, Path(provider): Path<String>) -> Response {
+    match state
+        .auth_provider(&provider)
+        .and_then(|provider| provider.redirect())
+    {
+        Some(url) => Redirect::to(url.as_str()).into_response(),
+        None => error(StatusCode::NOT_FOUND, AuthError::UnknownProvider),
+    }
+}
+
+async fn callback(
+    State(state): State<Shared>,
+    Path(provider): Path<String>,
+    Query(credentials): Query<HashMap<String, String>>,
+) -> Response {
+    sign_in(&state, &provider, credentials).await
+}
+
+/// Mints a session for whoever the provider says `credentials` belong to.
+async fn sign_in(
+    state: &ServerState,
+    provider: &str,
+    credentials: HashMap<String, String>,
+) -> Response {
+    let Some(provider) = state.auth_provider(provider) else {
+        return error(StatusCode::NOT_FOUND, AuthError::UnknownProvider);
+    };
+
+    match provider.authenticate(&credentials).await {
+        Ok(identity) => {
+            let session_id = state
+                .sequencer
+                .lock()
+                .unwrap()
+                .add_session(identity.clone());
+            match session_id {
+                Ok(session_id) => Json(SessionResponse {
+                    session_id,
+                    identity,
+                })
+                .into_response(),
+                Err(err) => error(StatusCode::FORBIDDEN, err),
+            }
+        }
+        Err(err @ AuthError::InvalidCredentials) => error(StatusCode::UNAUTHORIZED, err),
+        Err(err) => error(StatusCode::BAD_REQUEST, err),
+    }
+}
+
+async fn try_contribute(State(state): State<Shared>, headers: HeaderMap) -> Response {
+    let Some(session_id) = session_id(&headers) else {
+        return error(
+            StatusCode::UNAUTHORIZED,
+            TryContributeError::UnknownSessionId,
+        );
+    };
+
+    match state.sequencer.lock().unwrap().try_contribute(session_id) {
+        Ok(response) => Json(response).into_response(),
+        Err(err @ TryContributeError::UnknownSessionId) => error(StatusCode::UNAUTHORIZED, err),
+        Err(err) => error(StatusCode::BAD_REQUEST, err),
+    }
+}
+
+async fn contribute(
+    State(state): State<Shared>,
+    headers: HeaderMap,
+    Json(contribution): Json<BatchContribution>,
+) -> Response {
+    let Some(session_id) = session_id(&headers).map(ToString::to_string) else {
+        return error(
+            StatusCode::BAD_REQUEST,
+            ContributionError::Session(SessionError::InvalidSessionId),
+        );
+    };
+
+    let (index, previous) = match state
+        .sequencer
+        .lock()
+        .unwrap()
+        .receive_contribution(&session_id)
+    {
+        Ok(received) => received,
+        Err(err) => return error(StatusCode::BAD_REQUEST, err),
+    };
+
+    // the pairings take a while, so they're computed without holding the lock
+    let verified = tokio::task::spawn_blocking(move || {
+        verify_contribution(&previous, &contribution).map(|()| contribution)
+    })
+    .await;
+
+    let contribution = match verified {
+        Ok(Ok(contribution)) => contribution,
+        Ok(Err(err)) => {
+            // a rejected contribution ends the participant's turn
+            let _ = state
+                .sequencer
+                .lock()
+                .unwrap()
+                .abort_contribution(&session_id);
+            return error(StatusCode::BAD_REQUEST, err);
+        }
+        Err(err) => {
+            eprintln!("⚠️  Failed to verify a contribution: {err}");
+            let _ = state
+                .sequencer
+                .lock()
+                .unwrap()
+                .abort_contribution(&session_id);
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+
+    let commit = state.commit.lock().await;
+    let accepted =
+        state
+            .sequencer
+            .lock()
+            .unwrap()
+            .accept_contribution(&session_id, index, contribution);
+    let entry = match accepted {
+        Ok(entry) => entry,
+        Err(err) => return error(StatusCode::BAD_REQUEST, err),
+    };
+
+    // no receipt for a contribution that could be lost
+    let identity = entry.identity.clone();
+    let entry = match journal(&state, entry).await {
+        Ok(entry) => entry,
+        Err(err) => {
+            eprintln!("⚠️  Failed to record the contribution of {identity}: {err}");
+            state.sequencer.lock().unwrap().reopen_turn(&session_id);
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+
+    let receipt = state.sequencer.lock().unwrap().apply(&session_id, &entry);
+    drop(commit);
+
+    // the journal has it anyway, so it only needs to be checkpointed once in a while
+    if entry.index % CHECKPOINT_INTERVAL == 0 {
+        if let Err(err) = checkpoint(&state).await {
+            eprintln!("⚠️  Failed to save the transcript: {err}");
+        }
+    }
+
+    Json(receipt).into_response()
+}
+
+async fn abort(State(state): State<Shared>, headers: HeaderMap) -> Response {
+    let session_id = session_id(&headers).unwrap_or_default();
+
+    match state
+        .sequencer
+        .lock()
+        .unwrap()
+        .abort_contribution(session_id)
+    {
+        Ok(()) => Json(()).into_response(),
+        Err(err) => error(StatusCode::BAD_REQUEST, err),
+    }
+}
+
+/// Lets through requests that carry the admin token.
+async fn require_admin<B>(
+    State(state): State<Shared>,
+    headers: HeaderMap,
+    request: Request<B>,
+    next: Next<B>,
+) -> Response {
+    match (&state.admin_token, session_id(&headers)) {
+        // compared in constant time, so that response times don't give the token away
+        (Some(admin_token), Some(token))
+            if bool::from(admin_token.as_bytes().ct_eq(token.as_bytes())) =>
+        {
+            next.run(request).await
+        }
+        _ => error(StatusCode::UNAUTHORIZED, AdminError::InvalidToken),
+    }
+}
+
+async fn admin_lobby(State(state): State<Shared>) -> Response {
+    Json(state.sequencer.lock().unwrap().lobby_state()).into_response()
+}
+
+async fn admin_pause(State(state): State<Shared>) -> Response {
+    state.sequencer.lock().unwrap().set_paused(true);
+    Json(()).into_response()
+}
+
+async fn admin_resume(State(state): State<Shared>) -> Response {
+    state.sequencer.lock().unwrap().set_paused(false);
+    Json(()).into_response()
+}
+
+async fn admin_ban(State(state): State<Shared>, Json(request): Json<IdentityRequest>) -> Response {
+    state.sequencer.lock().unwrap().ban(&request.identity);
+    Json(()).into_response()
+}
+
+async fn admin_unban(
+    State(state): State<Shared>,
+    Json(request): Json<IdentityRequest>,
+) -> Response {
+    Json(state.sequencer.lock().unwrap().unban(&request.identity)).into_response()
+}
+
+async fn admin_abort(State(state): State<Shared>) -> Response {
+    match state.sequencer.lock().unwrap().force_abort() {
+        Some(identity) => Json(IdentityRequest { identity }).into_response(),
+        None => error(StatusCode::BAD_REQUEST, AdminError::NobodyContributing),
+    }
+}
+
+/// Publishes the transcript as it stands at `info/snapshot`.
+async fn admin_snapshot(State(state): State<Shared>) -> Response {
+    let (transcript, num_contributions) = {
+        let sequencer = state.sequencer.lock().unwrap();
+        let num_contributions = sequencer.status().num_contributions;
+        (sequencer.transcript().clone(), num_contributions)
+    };
+
+    match write_snapshot(&state, transcript).await {
+        Ok(bytes) => *state.snapshot.lock().unwrap() = Some(bytes),
+        Err(err) => {
+            eprintln!("⚠️  Failed to save the snapshot: {err}");
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    }
+
+    Json(SnapshotResponse { num_contributions }).into_response()
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::{
+        contribution::entropy::Entropy,
+        history::ContributionLog,
+        run_contribution,
+        sequencer::{types::TryContributeResponse, SequencerClient},
+        server::{
+            auth::{
+                tests::{oauth2_config, stand_in},
+                OAuth2Provider, TokenProvider,
+            },
+            lobby::{Lobby, LobbyConfig},
+        },
+        transcript::{tests::transcript_with, verify_transcript, SubCeremonyReport},
+    };
+    use std::{fs, thread::JoinHandle, time::Duration};
+    use tokio::sync::oneshot;
+
+    /// A server on localhost, running on its own thread until dropped.
+    pub(crate) struct TestServer {
+        pub url: Url,
+        shutdown: Option<oneshot::Sender<()>>,
+        thread: Option<JoinHandle<()>>,
+    }
+
+    impl TestServer {
+        pub(crate) fn start(
+            sequencer: Sequencer,
+            store: Option<Store>,
+            auth: Vec<Box<dyn AuthProvider>>,
+        ) -> Self {
+            Self::spawn(|url| {
+                router(Arc::new(ServerState {
+                    store,
+                    auth,
+                    ..ServerState::new(sequencer, url.clone())
+                }))
+            })
+        }
+
+        /// Serves the app made by `app` for the URL of the server.
+        pub(crate) fn spawn<F: FnOnce(&Url) -> Router>(app: F) -> Self {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            let url = Url::parse(&format!("http://{}/", listener.local_addr().unwrap())).unwrap();
+            let app = app(&url);
+            let (shutdown, stopped) = oneshot::channel::<()>();
+
+            let thread = std::thread::spawn(move || {
+                tokio::runtime::Runtime::new().unwrap().block_on(async {
+                    axum::Server::from_tcp(listener)
+                        .unwrap()
+                        .serve(app.into_make_service())
+                        .with_graceful_shutdown(async {
+                            let _ = stopped.await;
+                        })
+                        .await
+                        .unwrap()
+                })
+            });
+
+            Self {
+                url,
+                shutdown: Some(shutdown),
+                thread: Some(thread),
+            }
+        }
+
+        pub(crate) fn client(&self) -> SequencerClient {
+            SequencerClient::new(self.url.clone())
+        }
+    }
+
+    impl Drop for TestServer {
+        fn drop(&mut self) {
+            if let Some(shutdown) = self.shutdown.take() {
+                let _ = shutdown.send(());
+            }
+            if let Some(thread) = self.thread.take() {
+                let _ = thread.join();
+            }
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn sign_in_with_discovered_providers() {
+        let stand_in = stand_in();
+        let server = TestServer::spawn(|url| {
+            let tokens = HashMap::from([("secret".to_string(), "eth|0x01".to_string())]);
+            let (transcript, _) = transcript_with(&[]);
+            let lobby = Lobby::new(LobbyConfig::default());
+
+            let sequencer = Sequencer::new(transcript, HashMap::new(), lobby);
+
+            router(Arc::new(ServerState {
+                auth: vec![
+                    Box::new(TokenProvider::new(tokens)),
+                    Box::new(OAuth2Provider::new(oauth2_config(&stand_in.url, url))),
+                ],
+                ..ServerState::new(sequencer, url.clone())
+            }))
+        });
+        let client = server.client();
+
+        let providers = client.auth_providers().await.unwrap().unwrap();
+        assert_eq!(providers.len(), 2);
+        assert_eq!(providers[0].method, AuthMethod::Token);
+        let AuthMethod::Browser { url } = &providers[1].method else {
+            panic!("OAuth2 sign-ins happen in the browser");
+        };
+        assert_eq!(*url, format!("{}auth/oauth/authorize", server.url));
+
+        let session = client.login("token", "secret").await.unwrap();
+        assert_eq!(session.identity, "eth|0x01");
+        assert!(client.login("token", "guess").await.is_err());
+        assert!(client.login("nobody", "secret").await.is_err());
+
+        // the way a browser would, through the stand-in and back
+        let session: SessionResponse = reqwest::get(url).await.unwrap().json().await.unwrap();
+        assert_eq!(session.identity, "oauth|42");
+        assert_eq!(
+            client.session(&session.session_id).await.unwrap().identity,
+            "oauth|42"
+        );
+        assert!(client.session("made-up").await.is_err());
+
+        assert!(matches!(
+            client.try_contribute(&session.session_id).await,
+            Ok(TryContributeResponse::BatchContribution(_))
+        ));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn operate_the_ceremony() {
+        use crate::sequencer::{types::AdminError, SequencerClientError};
+
+        let dir = std::env::temp_dir().join(format!("majlis-kzg-admin-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let store = Store::new(dir.join("transcript.json"));
+        store.checkpoint(&transcript_with(&[]).0).unwrap();
+
+        let tokens = HashMap::from([
+            ("a".to_string(), "eth|0x01".to_string()),
+            ("b".to_string(), "eth|0x02".to_string()),
+        ]);
+        let lobby = Lobby::new(LobbyConfig {
+            min_poll_interval: Duration::ZERO,
+            ..Default::default()
+        });
+        let sequencer = Sequencer::new(store.recover().unwrap(), HashMap::new(), lobby);
+        let server = TestServer::spawn(|url| {
+            router(Arc::new(ServerState {
+                store: Some(store),
+                auth: vec![Box::new(TokenProvider::new(tokens))],
+                admin_token: Some("operator".to_string()),
+                ..ServerState::new(sequencer, url.clone())
+            }))
+        });
+        let client = server.client();
+        let admin = client.admin("operator");
+
+        assert!(matches!(
+            client.admin("guess").lobby().await,
+            Err(SequencerClientError::SequencerError(err)) if err.code == AdminError::InvalidToken
+        ));
+
+        let alice = client.login("token", "a").await.unwrap().session_id;
+        let bob = client.login("token", "b").await.unwrap().session_id;
+        admin.pause().await.unwrap();
+        assert!(matches!(
+            client.try_contribute(&alice).await,
+            Ok(TryContributeResponse::InProgress(_))
+        ));
+        admin.resume().await.unwrap();
+        assert!(matches!(
+            client.try_contribute(&alice).await,
+            Ok(TryContributeResponse::BatchContribution(_))
+        ));
+        client.try_contribute(&bob).await.unwrap();
+
+        let lobby = admin.lobby().await.unwrap();
+        assert_eq!(lobby.contributing.unwrap().identity, "eth|0x01");
+        assert_eq!(lobby.waiting, vec!["eth|0x02".to_string()]);
+
+        assert_eq!(admin.abort().await.unwrap(), "eth|0x01");
+        assert!(matches!(
+            admin.abort().await,
+            Err(SequencerClientError::SequencerError(err))
+                if err.code == AdminError::NobodyContributing
+        ));
+
+        admin.ban("eth|0x02").await.unwrap();
+        assert!(client.try_contribute(&bob).await.is_err());
+        assert!(matches!(
+            client.login("token", "b").await,
+            Err(SequencerClientError::SequencerError(err)) if err.code == AuthError::Banned
+        ));
+        assert!(admin.unban("eth|0x02").await.unwrap());
+        client.login("token", "b").await.unwrap();
+
+        let snapshot_url = server.url.join("info/snapshot").unwrap();
+        assert_eq!(
+            reqwest::get(snapshot_url.clone()).await.unwrap().status(),
+            StatusCode::NOT_FOUND
+        );
+        assert_eq!(admin.snapshot().await.unwrap().num_contributions, 0);
+        let snapshot: crate::contribution::types::BatchTranscript = reqwest::get(snapshot_url)
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(snapshot.participant_ids.len(), 1);
+        assert!(Store::new(dir.join("transcript.json"))
+            .snapshot()
+            .unwrap()
+            .is_some());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn reject_powers_outside_subgroup() {
+        use crate::{
+            contribution::{point::G1Point, update_batch},
+            sequencer::{
+                types::{CeremonyError, ContributionError},
+                SequencerClientError,
+            },
+        };
+
+        let dir = std::env::temp_dir().join(format!("majlis-kzg-subgroup-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let store = Store::new(dir.join("transcript.json"));
+        store.checkpoint(&transcript_with(&[]).0).unwrap();
+
+        let sessions = HashMap::from([("alice".to_string(), "eth|0x01".to_string())]);
+        let lobby = Lobby::new(LobbyConfig::default());
+        let sequencer = Sequencer::new(store.recover().unwrap(), sessions, lobby);
+        let server = TestServer::start(sequencer, Some(store), Vec::new());
+        let client = server.client();
+
+        let Ok(TryContributeResponse::BatchContribution(mut batch)) =
+            client.try_contribute("alice").await
+        else {
+            panic!("alice is the only participant");
+        };
+        update_batch(&mut batch, "eth|0x01", Entropy::new());
+        // on the curve, but outside the prime-order subgroup
+        batch.contributions[1].powers_of_tau.g1_powers[3] = format!("0x80{}04", "00".repeat(46))
+            .parse::<G1Point>()
+            .unwrap();
+
+        assert!(matches!(
+            client.contribute(&batch, "alice").await,
+            Err(SequencerClientError::SequencerError(err))
+                if err.code == ContributionError::Ceremony(CeremonyError::InvalidG1Power)
+        ));
+
+        // nothing was journaled that would keep the server from coming back up
+        drop(server);
+        let recovered = Store::new(dir.join("transcript.json")).recover().unwrap();
+        assert_eq!(recovered.participant_ids.len(), 1);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn resubmit_contribution_that_failed_to_record() {
+        use crate::{contribution::update_batch, sequencer::SequencerClientError};
+
+        let dir =
+            std::env::temp_dir().join(format!("majlis-kzg-unrecorded-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let store = Store::new(dir.join("transcript.json"));
+        store.checkpoint(&transcript_with(&[]).0).unwrap();
+        let sessions = HashMap::from([
+            ("alice".to_string(), "eth|0x01".to_string()),
+            ("bob".to_string(), "eth|0x02".to_string()),
+        ]);
+        let lobby = Lobby::new(LobbyConfig {
+            min_poll_interval: Duration::ZERO,
+            ..Default::default()
+        });
+        let sequencer = Sequencer::new(store.recover().unwrap(), sessions, lobby);
+        // the journal can't be opened
+        let journal_path = dir.join("transcript.json.journal");
+        fs::create_dir(&journal_path).unwrap();
+        let server = TestServer::start(sequencer, Some(store), Vec::new());
+        let client = server.client();
+
+        let Ok(TryContributeResponse::BatchContribution(mut batch)) =
+            client.try_contribute("alice").await
+        else {
+            panic!("alice is the first participant");
+        };
+        update_batch(&mut batch, "eth|0x01", Entropy::new());
+
+        assert!(matches!(
+            client.contribute(&batch, "alice").await,
+            Err(SequencerClientError::UnexpectedStatus { status, .. })
+                if status == StatusCode::INTERNAL_SERVER_ERROR
+        ));
+        assert!(matches!(
+            client.try_contribute("bob").await,
+            Ok(TryContributeResponse::InProgress(_))
+        ));
+
+        fs::remove_dir(&journal_path).unwrap();
+        client.contribute(&batch, "alice").await.unwrap();
+        assert_eq!(
+            client.current_state().await.unwrap().participant_ids.len(),
+            2
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn clients_contribute_to_self_hosted_ceremony() {
+        let identities = [
+            ("alice", "eth|0x0000000000000000000000000000000000000001"),
+            ("bob", "eth|0x0000000000000000000000000000000000000002"),
+        ];
+        let sessions = identities
+            .iter()
+            .map(|(session, identity)| (session.to_string(), identity.to_string()))
+            .collect::<HashMap<_, _>>();
+        // the client polls far more often than it would for real
+        let lobby = Lobby::new(LobbyConfig {
+            min_poll_interval: Duration::ZERO,
+            ..Default::default()
+        });
+
+        let dir = std::env::temp_dir().join(format!("majlis-kzg-serve-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let log = ContributionLog::new(dir.join("log"));
+
+        let store = Store::new(dir.join("transcript.json"));
+        store.checkpoint(&transcript_with(&[]).0).unwrap();
+        let sequencer = Sequencer::new(store.recover().unwrap(), sessions, lobby);
+        #[cfg(feature = "eth")]
+        let sequencer =
+            sequencer.with_signer(ethers::signers::LocalWallet::new(&mut rand_core::OsRng));
+        let server = TestServer::start(sequencer, Some(store), Vec::new());
+
+        for (session_id, identity) in identities {
+            async_std::task::block_on(run_contribution(
+                &server.client(),
+                session_id,
+                identity,
+                Entropy::new(),
+                dir.join(format!("{session_id}.json")),
+                &log,
+                Duration::from_millis(10),
+            ))
+            .unwrap();
+        }
+
+        let transcript = server.client().current_state().await.unwrap();
+        assert_eq!(transcript.participant_ids.len(), 3);
+        assert!(verify_transcript(&transcript)
+            .iter()
+            .all(SubCeremonyReport::passed));
+
+        // once contributed, a session has nothing left to do
+        assert!(server
+            .client()
+            .contribute(&transcript_with(&[]).1, "alice")
+            .await
+            .is_err());
+
+        #[cfg(feature = "eth")]
+        assert!(log
+            .entries()
+            .unwrap()
+            .iter()
+            .all(|entry| entry.receipt_verification
+                == Some(crate::sequencer::types::ReceiptVerification::Verified)));
+
+        // every contribution survives a restart
+        let recovered = Store::new(dir.join("transcript.json")).recover().unwrap();
+        assert_eq!(recovered.participant_ids, transcript.participant_ids);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
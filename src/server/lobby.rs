@@ -0,0 +1,401 @@
+//! Turn-taking: who waits in the lobby, whose turn it is, and for how long.
+
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+use crate::sequencer::types::{ContributeError, TryContributeError};
+
+/// Source of the current time, so that tests don't have to wait for timeouts.
+pub trait Clock: Send {
+    fn now(&self) -> Instant;
+}
+
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+pub struct LobbyConfig {
+    /// Polls closer together than this are answered with `TryContributeError::RateLimited`.
+    pub min_poll_interval: Duration,
+    /// How long a participant may take to contribute once it's their turn.
+    pub contribution_timeout: Duration,
+    /// Participants who haven't polled for this long are dropped from the lobby.
+    pub poll_timeout: Duration,
+}
+
+impl Default for LobbyConfig {
+    fn default() -> Self {
+        // the client polls every 4 seconds
+        Self {
+            min_poll_interval: Duration::from_secs(2),
+            contribution_timeout: Duration::from_secs(180),
+            poll_timeout: Duration::from_secs(60),
+        }
+    }
+}
+
+/// Answer to a participant polling the lobby.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Poll {
+    YourTurn,
+    Waiting { ahead: usize },
+}
+
+struct Waiting {
+    session_id: String,
+    last_poll: Instant,
+}
+
+struct Turn {
+    session_id: String,
+    deadline: Instant,
+    /// Whether the contribution has been received. It's no longer subject to the deadline while
+    /// it's being verified, and no other one is taken in meanwhile.
+    submitted: bool,
+}
+
+/// First come, first served: participants wait in the order they first polled, and the one in
+/// front gets the turn on their next poll once nobody else is contributing.
+pub struct Lobby {
+    config: LobbyConfig,
+    clock: Box<dyn Clock>,
+    waiting: VecDeque<Waiting>,
+    turn: Option<Turn>,
+    /// Whether turns are being handed out.
+    paused: bool,
+}
+
+impl Lobby {
+    pub fn new(config: LobbyConfig) -> Self {
+        Self::with_clock(config, SystemClock)
+    }
+
+    pub fn with_clock<C: Clock + 'static>(config: LobbyConfig, clock: C) -> Self {
+        Self {
+            config,
+            clock: Box::new(clock),
+            waiting: VecDeque::new(),
+            turn: None,
+            paused: false,
+        }
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Stops or resumes handing out turns. Whoever is contributing can finish.
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+    }
+
+    /// Session ID of whoever is contributing, and the time they have left, if they haven't
+    /// submitted their contribution yet.
+    pub fn turn(&self) -> Option<(&str, Option<Duration>)> {
+        let now = self.clock.now();
+        self.turn.as_ref().map(|turn| {
+            let left = (!turn.submitted).then(|| turn.deadline.saturating_duration_since(now));
+            (turn.session_id.as_str(), left)
+        })
+    }
+
+    /// Session IDs of the participants waiting for their turn, in order.
+    pub fn waiting(&self) -> impl Iterator<Item = &str> {
+        let now = self.clock.now();
+        self.waiting
+            .iter()
+            .filter(move |waiting| now.duration_since(waiting.last_poll) < self.config.poll_timeout)
+            .map(|waiting| waiting.session_id.as_str())
+    }
+
+    /// Number of participants waiting for their turn.
+    pub fn len(&self) -> usize {
+        self.waiting().count()
+    }
+
+    /// Enters the lobby or keeps the participant's place in it, and gives them the turn if it's
+    /// free and they are first in line.
+    pub fn try_contribute(&mut self, session_id: &str) -> Result<Poll, TryContributeError> {
+        let now = self.clock.now();
+        self.evict(now);
+
+        if self.is_turn_of(session_id) {
+            return Ok(Poll::YourTurn);
+        }
+
+        match self.position(session_id) {
+            Some(position) => {
+                let waiting = &mut self.waiting[position];
+                if now.duration_since(waiting.last_poll) < self.config.min_poll_interval {
+                    return Err(TryContributeError::RateLimited);
+                }
+                waiting.last_poll = now;
+            }
+            None => self.waiting.push_back(Waiting {
+                session_id: session_id.to_string(),
+                last_poll: now,
+            }),
+        }
+
+        if self.turn.is_none() && !self.paused && self.position(session_id) == Some(0) {
+            self.waiting.pop_front();
+            self.turn = Some(Turn {
+                session_id: session_id.to_string(),
+                deadline: now + self.config.contribution_timeout,
+                submitted: false,
+            });
+            return Ok(Poll::YourTurn);
+        }
+
+        let ahead =
+            self.position(session_id).unwrap_or_default() + usize::from(self.turn.is_some());
+        Ok(Poll::Waiting { ahead })
+    }
+
+    /// Checks that it's the participant's turn, and that they haven't run out of time.
+    pub fn check_turn(&mut self, session_id: &str) -> Result<(), ContributeError> {
+        self.evict(self.clock.now());
+
+        match self.is_turn_of(session_id) {
+            true => Ok(()),
+            false => Err(ContributeError::NotUsersTurn),
+        }
+    }
+
+    /// Marks the participant's contribution as received, in time. Another one isn't taken in
+    /// until the turn is over or reopened, so that it's only ever verified once at a time.
+    pub fn submit(&mut self, session_id: &str) -> Result<(), ContributeError> {
+        self.check_turn(session_id)?;
+        if let Some(turn) = &mut self.turn {
+            if turn.submitted {
+                return Err(ContributeError::AlreadySubmitted);
+            }
+            turn.submitted = true;
+        }
+
+        Ok(())
+    }
+
+    /// Lets the participant submit again, with as much time as at the start of their turn.
+    pub fn reopen(&mut self, session_id: &str) {
+        let deadline = self.clock.now() + self.config.contribution_timeout;
+        if let Some(turn) = self
+            .turn
+            .as_mut()
+            .filter(|turn| turn.session_id == session_id)
+        {
+            turn.deadline = deadline;
+            turn.submitted = false;
+        }
+    }
+
+    /// Ends the participant's turn, whether they contributed or gave up.
+    pub fn end_turn(&mut self, session_id: &str) -> Result<(), ContributeError> {
+        self.check_turn(session_id)?;
+        self.turn = None;
+
+        Ok(())
+    }
+
+    /// Ends the turn of whoever is contributing, and returns their session ID.
+    pub fn force_end_turn(&mut self) -> Option<String> {
+        self.turn.take().map(|turn| turn.session_id)
+    }
+
+    /// Takes the participant out of the lobby, ending their turn if it's theirs.
+    pub fn remove(&mut self, session_id: &str) {
+        if self.is_turn_of(session_id) {
+            self.turn = None;
+        }
+        self.waiting
+            .retain(|waiting| waiting.session_id != session_id);
+    }
+
+    fn is_turn_of(&self, session_id: &str) -> bool {
+        self.turn
+            .as_ref()
+            .is_some_and(|turn| turn.session_id == session_id)
+    }
+
+    fn position(&self, session_id: &str) -> Option<usize> {
+        self.waiting
+            .iter()
+            .position(|waiting| waiting.session_id == session_id)
+    }
+
+    /// Drops the participant who ran out of time to contribute and those who stopped polling.
+    fn evict(&mut self, now: Instant) {
+        if self
+            .turn
+            .as_ref()
+            .is_some_and(|turn| !turn.submitted && now >= turn.deadline)
+        {
+            self.turn = None;
+        }
+
+        let poll_timeout = self.config.poll_timeout;
+        self.waiting
+            .retain(|waiting| now.duration_since(waiting.last_poll) < poll_timeout);
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    /// A clock that only moves when told to.
+    #[derive(Clone)]
+    pub(crate) struct ManualClock(Arc<Mutex<Instant>>);
+
+    impl ManualClock {
+        pub(crate) fn new() -> Self {
+            Self(Arc::new(Mutex::new(Instant::now())))
+        }
+
+        pub(crate) fn advance(&self, duration: Duration) {
+            *self.0.lock().unwrap() += duration;
+        }
+    }
+
+    impl Clock for ManualClock {
+        fn now(&self) -> Instant {
+            *self.0.lock().unwrap()
+        }
+    }
+
+    fn lobby() -> (Lobby, ManualClock) {
+        let clock = ManualClock::new();
+        let config = LobbyConfig {
+            min_poll_interval: Duration::from_secs(2),
+            contribution_timeout: Duration::from_secs(30),
+            poll_timeout: Duration::from_secs(10),
+        };
+        (Lobby::with_clock(config, clock.clone()), clock)
+    }
+
+    #[test]
+    fn first_come_first_served() {
+        let (mut lobby, clock) = lobby();
+
+        assert_eq!(lobby.try_contribute("alice"), Ok(Poll::YourTurn));
+        assert_eq!(lobby.try_contribute("bob"), Ok(Poll::Waiting { ahead: 1 }));
+        assert_eq!(
+            lobby.try_contribute("carol"),
+            Ok(Poll::Waiting { ahead: 2 })
+        );
+        assert_eq!(lobby.len(), 2);
+
+        lobby.end_turn("alice").unwrap();
+        clock.advance(Duration::from_secs(2));
+
+        // carol polls first, but bob was there before her
+        assert_eq!(
+            lobby.try_contribute("carol"),
+            Ok(Poll::Waiting { ahead: 1 })
+        );
+        assert_eq!(lobby.try_contribute("bob"), Ok(Poll::YourTurn));
+        assert_eq!(lobby.try_contribute("bob"), Ok(Poll::YourTurn));
+        assert_eq!(lobby.len(), 1);
+    }
+
+    #[test]
+    fn rate_limit_polls() {
+        let (mut lobby, clock) = lobby();
+        lobby.try_contribute("alice").unwrap();
+
+        lobby.try_contribute("bob").unwrap();
+        clock.advance(Duration::from_secs(1));
+        assert_eq!(
+            lobby.try_contribute("bob"),
+            Err(TryContributeError::RateLimited)
+        );
+
+        clock.advance(Duration::from_secs(1));
+        assert_eq!(lobby.try_contribute("bob"), Ok(Poll::Waiting { ahead: 1 }));
+    }
+
+    #[test]
+    fn evict_participants_who_time_out() {
+        let (mut lobby, clock) = lobby();
+        lobby.try_contribute("alice").unwrap();
+        lobby.try_contribute("bob").unwrap();
+        lobby.try_contribute("carol").unwrap();
+
+        // bob stops polling, carol keeps at it
+        for _ in 0..4 {
+            clock.advance(Duration::from_secs(6));
+            lobby.try_contribute("carol").unwrap();
+        }
+        assert_eq!(lobby.len(), 1);
+        lobby.check_turn("alice").unwrap();
+
+        // alice ran out of time, and bob lost his place
+        clock.advance(Duration::from_secs(6));
+        assert_eq!(
+            lobby.check_turn("alice"),
+            Err(ContributeError::NotUsersTurn)
+        );
+        assert_eq!(lobby.try_contribute("carol"), Ok(Poll::YourTurn));
+        assert_eq!(lobby.end_turn("alice"), Err(ContributeError::NotUsersTurn));
+        assert_eq!(lobby.try_contribute("bob"), Ok(Poll::Waiting { ahead: 1 }));
+    }
+
+    #[test]
+    fn pause_and_force_end_turn() {
+        let (mut lobby, clock) = lobby();
+        lobby.try_contribute("alice").unwrap();
+        lobby.try_contribute("bob").unwrap();
+        lobby.try_contribute("carol").unwrap();
+        assert_eq!(lobby.turn(), Some(("alice", Some(Duration::from_secs(30)))));
+
+        lobby.set_paused(true);
+        assert_eq!(lobby.force_end_turn(), Some("alice".to_string()));
+        assert_eq!(lobby.force_end_turn(), None);
+
+        clock.advance(Duration::from_secs(2));
+        assert_eq!(lobby.try_contribute("bob"), Ok(Poll::Waiting { ahead: 0 }));
+
+        lobby.set_paused(false);
+        lobby.remove("bob");
+        clock.advance(Duration::from_secs(2));
+        assert_eq!(lobby.try_contribute("carol"), Ok(Poll::YourTurn));
+        assert_eq!(lobby.waiting().count(), 0);
+    }
+
+    #[test]
+    fn no_deadline_once_submitted() {
+        let (mut lobby, clock) = lobby();
+        lobby.try_contribute("alice").unwrap();
+
+        lobby.submit("alice").unwrap();
+        clock.advance(Duration::from_secs(60));
+        lobby.end_turn("alice").unwrap();
+
+        assert_eq!(lobby.submit("bob"), Err(ContributeError::NotUsersTurn));
+    }
+
+    #[test]
+    fn submit_once_until_reopened() {
+        let (mut lobby, clock) = lobby();
+        lobby.try_contribute("alice").unwrap();
+        lobby.submit("alice").unwrap();
+        clock.advance(Duration::from_secs(60));
+
+        assert_eq!(
+            lobby.submit("alice"),
+            Err(ContributeError::AlreadySubmitted)
+        );
+
+        lobby.reopen("bob");
+        lobby.reopen("alice");
+        assert_eq!(lobby.turn(), Some(("alice", Some(Duration::from_secs(30)))));
+        lobby.submit("alice").unwrap();
+    }
+}
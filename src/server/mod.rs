@@ -0,0 +1,561 @@
+#[cfg(feature = "eth")]
+use ethers::signers::{LocalWallet, Signer};
+use std::collections::{HashMap, HashSet};
+
+pub mod auth;
+pub mod http;
+pub mod lobby;
+pub mod store;
+
+use crate::{
+    contribution::{
+        subgroup::Group,
+        subgroup_checks,
+        types::{BatchContribution, BatchTranscript},
+        validation::validate_contribution,
+        verification::verify_update,
+    },
+    sequencer::types::{
+        AuthError, CeremonyError, CeremonyStatus, ContributeError, ContributionAbortError,
+        ContributionError, ContributionReceipt, Contributor, LobbyState, ReceiptPayload,
+        SessionError, TryContributeError, TryContributeResponse,
+    },
+    transcript::{current_contribution, signatures::verify_signature},
+};
+use lobby::{Lobby, Poll};
+use store::JournalEntry;
+
+/// State of a self-hosted ceremony: the transcript so far, who may take part, and whose turn it
+/// is. Contributions are verified outside of it (see [`verify_contribution`]), so that a long
+/// verification doesn't hold up everyone else.
+pub struct Sequencer {
+    transcript: BatchTranscript,
+    /// Identity of every session ID.
+    sessions: HashMap<String, String>,
+    lobby: Lobby,
+    /// Identities that may not take part, lowercased.
+    banned: HashSet<String>,
+    /// Account that signs the receipts.
+    #[cfg(feature = "eth")]
+    signer: Option<LocalWallet>,
+}
+
+impl Sequencer {
+    pub fn new(
+        transcript: BatchTranscript,
+        sessions: HashMap<String, String>,
+        lobby: Lobby,
+    ) -> Self {
+        Self {
+            transcript,
+            sessions,
+            lobby,
+            banned: HashSet::new(),
+            #[cfg(feature = "eth")]
+            signer: None,
+        }
+    }
+
+    /// Signs receipts with `wallet`, whose address becomes the `sequencer_address`.
+    #[cfg(feature = "eth")]
+    pub fn with_signer(mut self, wallet: LocalWallet) -> Self {
+        self.signer = Some(wallet);
+        self
+    }
+
+    pub fn transcript(&self) -> &BatchTranscript {
+        &self.transcript
+    }
+
+    pub fn status(&self) -> CeremonyStatus {
+        CeremonyStatus {
+            lobby_size: self.lobby.len() as i32,
+            num_contributions: self.transcript.participant_ids.len().saturating_sub(1) as i32,
+            sequencer_address: self.address(),
+        }
+    }
+
+    /// Mints a session for a participant who proved they are `identity`, unless they are banned.
+    pub fn add_session(&mut self, identity: String) -> Result<String, AuthError> {
+        if self.banned.contains(&identity.to_lowercase()) {
+            return Err(AuthError::Banned);
+        }
+
+        let session_id = auth::random_hex();
+        self.sessions.insert(session_id.clone(), identity);
+        Ok(session_id)
+    }
+
+    pub fn identity(&self, session_id: &str) -> Option<&str> {
+        self.sessions.get(session_id).map(String::as_str)
+    }
+
+    /// Gives the batch to contribute to if nobody else is contributing, or keeps the participant
+    /// in the lobby.
+    pub fn try_contribute(
+        &mut self,
+        session_id: &str,
+    ) -> Result<TryContributeResponse, TryContributeError> {
+        if !self.sessions.contains_key(session_id) {
+            return Err(TryContributeError::UnknownSessionId);
+        }
+
+        match self.lobby.try_contribute(session_id)? {
+            Poll::YourTurn => Ok(TryContributeResponse::BatchContribution(
+                self.current_contribution(),
+            )),
+            Poll::Waiting { ahead } if self.lobby.is_paused() => {
+                Ok(TryContributeResponse::InProgress(format!(
+                    "contributions are paused, {ahead} participant(s) ahead of you"
+                )))
+            }
+            Poll::Waiting { ahead } => Ok(TryContributeResponse::InProgress(format!(
+                "{ahead} participant(s) ahead of you"
+            ))),
+        }
+    }
+
+    /// Takes in the participant's contribution for verification, which stops their turn from
+    /// timing out, and returns the batch they must have built on along with the index their
+    /// contribution is going to have.
+    pub fn receive_contribution(
+        &mut self,
+        session_id: &str,
+    ) -> Result<(usize, BatchContribution), ContributionError> {
+        self.check_session(session_id)?;
+        self.lobby
+            .submit(session_id)
+            .map_err(ContributionError::Contribute)?;
+
+        Ok((
+            self.transcript.participant_ids.len(),
+            self.current_contribution(),
+        ))
+    }
+
+    /// Takes the participant's verified contribution, received as the `index`th one. It's only
+    /// added to the transcript by [`Sequencer::apply`], once the returned entry has been made
+    /// durable (see [`Store::append`](store::Store::append)), and their turn goes on until then so
+    /// that nobody else is handed the transcript without it.
+    pub fn accept_contribution(
+        &mut self,
+        session_id: &str,
+        index: usize,
+        contribution: BatchContribution,
+    ) -> Result<JournalEntry, ContributionError> {
+        self.check_session(session_id)?;
+        // the turn may have been aborted during the verification, and others may have
+        // contributed since, in which case the contribution no longer builds on the transcript
+        if index != self.transcript.participant_ids.len() {
+            return Err(ContributionError::Contribute(ContributeError::NotUsersTurn));
+        }
+        self.lobby
+            .check_turn(session_id)
+            .map_err(ContributionError::Contribute)?;
+
+        Ok(JournalEntry {
+            index,
+            identity: self.sessions[session_id].clone(),
+            contribution,
+        })
+    }
+
+    /// Adds an accepted contribution to the transcript, ends the turn of the participant who made
+    /// it and returns their receipt.
+    pub fn apply(&mut self, session_id: &str, entry: &JournalEntry) -> ContributionReceipt {
+        extend_transcript(&mut self.transcript, entry);
+        // unless the operator aborted it in the meantime
+        let _ = self.lobby.end_turn(session_id);
+        self.receipt(&entry.identity)
+    }
+
+    /// Gives the participant their turn back when their accepted contribution couldn't be
+    /// recorded, so that they can submit it again.
+    pub fn reopen_turn(&mut self, session_id: &str) {
+        self.lobby.reopen(session_id);
+    }
+
+    /// Ends the participant's turn without changing the transcript.
+    pub fn abort_contribution(&mut self, session_id: &str) -> Result<(), ContributionAbortError> {
+        if !self.sessions.contains_key(session_id) {
+            return Err(ContributionAbortError::Session(
+                SessionError::InvalidSessionId,
+            ));
+        }
+
+        self.lobby
+            .end_turn(session_id)
+            .map_err(ContributionAbortError::Contribute)
+    }
+
+    /// What the operator sees of the lobby.
+    pub fn lobby_state(&self) -> LobbyState {
+        let mut banned = self.banned.iter().cloned().collect::<Vec<_>>();
+        banned.sort();
+
+        LobbyState {
+            paused: self.lobby.is_paused(),
+            contributing: self.lobby.turn().map(|(session_id, left)| Contributor {
+                identity: self.sessions[session_id].clone(),
+                seconds_left: left.map(|left| left.as_secs()),
+            }),
+            waiting: self
+                .lobby
+                .waiting()
+                .map(|session_id| self.sessions[session_id].clone())
+                .collect(),
+            banned,
+        }
+    }
+
+    /// Stops or resumes handing out turns.
+    pub fn set_paused(&mut self, paused: bool) {
+        self.lobby.set_paused(paused);
+    }
+
+    /// Ends the turn of whoever is contributing, and returns their identity. A contribution
+    /// that is being verified is then turned down.
+    pub fn force_abort(&mut self) -> Option<String> {
+        let session_id = self.lobby.force_end_turn()?;
+        Some(self.sessions[&session_id].clone())
+    }
+
+    /// Signs the participant out everywhere, takes them out of the lobby and keeps them from
+    /// signing in again.
+    pub fn ban(&mut self, identity: &str) {
+        let identity = identity.to_lowercase();
+        let session_ids = self
+            .sessions
+            .iter()
+            .filter(|(_, session_identity)| session_identity.to_lowercase() == identity)
+            .map(|(session_id, _)| session_id.clone())
+            .collect::<Vec<_>>();
+
+        for session_id in session_ids {
+            self.lobby.remove(&session_id);
+            self.sessions.remove(&session_id);
+        }
+        self.banned.insert(identity);
+    }
+
+    /// Lets a banned participant sign in again. Returns whether they were banned.
+    pub fn unban(&mut self, identity: &str) -> bool {
+        self.banned.remove(&identity.to_lowercase())
+    }
+
+    fn check_session(&self, session_id: &str) -> Result<(), ContributionError> {
+        match self.sessions.contains_key(session_id) {
+            true => Ok(()),
+            false => Err(ContributionError::Session(SessionError::InvalidSessionId)),
+        }
+    }
+
+    fn current_contribution(&self) -> BatchContribution {
+        current_contribution(&self.transcript)
+    }
+
+    /// Unsigned receipts come from the zero address.
+    fn address(&self) -> String {
+        #[cfg(feature = "eth")]
+        if let Some(signer) = &self.signer {
+            return format!("{:?}", signer.address());
+        }
+
+        "0x0000000000000000000000000000000000000000".to_string()
+    }
+
+    fn receipt(&self, identity: &str) -> ContributionReceipt {
+        let payload = ReceiptPayload {
+            identity: identity.to_string(),
+            witness: self
+                .transcript
+                .transcripts
+                .iter()
+                .filter_map(|transcript| transcript.witness.pot_pubkeys.last().copied())
+                .collect(),
+        };
+
+        #[cfg(feature = "eth")]
+        if let Some(signer) = &self.signer {
+            return ContributionReceipt::sign(&payload, signer);
+        }
+
+        ContributionReceipt::new(payload.to_canonical_json(), String::new())
+    }
+}
+
+fn extend_transcript(transcript: &mut BatchTranscript, entry: &JournalEntry) {
+    for (transcript, contr) in transcript
+        .transcripts
+        .iter_mut()
+        .zip(&entry.contribution.contributions)
+    {
+        let witness = &mut transcript.witness;
+        witness
+            .running_products
+            .push(contr.powers_of_tau.g1_powers[1]);
+        witness.pot_pubkeys.push(contr.pot_pubkey);
+
+        // like the public sequencer, keep only signatures that verify
+        let signature = contr.bls_signature.clone().unwrap_or_default();
+        let valid = verify_signature(&entry.identity, &contr.pot_pubkey, &signature);
+        witness
+            .bls_signatures
+            .push(if valid { signature } else { String::new() });
+
+        transcript.powers_of_tau.g1_powers = contr.powers_of_tau.g1_powers.clone();
+        transcript.powers_of_tau.g2_powers = contr.powers_of_tau.g2_powers.clone();
+    }
+
+    transcript.participant_ids.push(entry.identity.clone());
+    transcript.participant_ecds_signatures.push(
+        entry
+            .contribution
+            .ecdsa_signature
+            .clone()
+            .unwrap_or_default(),
+    );
+}
+
+/// Runs every check the sequencer applies to a contribution: the structural checks with their
+/// `CeremonyError` codes, then the subgroup checks of the powers, then the pairing checks.
+pub fn verify_contribution(
+    previous: &BatchContribution,
+    contribution: &BatchContribution,
+) -> Result<(), ContributionError> {
+    validate_contribution(previous, contribution)?;
+    // the points were decoded without checks, and the pairings don't make up for it
+    subgroup_checks(contribution).map_err(|err| {
+        ContributionError::Ceremony(match err.group {
+            Group::G1 => CeremonyError::InvalidG1Power,
+            Group::G2 => CeremonyError::InvalidG2Power,
+        })
+    })?;
+    verify_update(previous, contribution).map_err(|err| ContributionError::Ceremony(err.error))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        contribution::{entropy::Entropy, update_batch},
+        server::lobby::LobbyConfig,
+        transcript::{tests::transcript_with, verify_transcript, SubCeremonyReport},
+    };
+    use std::time::Duration;
+
+    const ALICE: &str = "eth|0x0000000000000000000000000000000000000001";
+    const BOB: &str = "git|26515232|@kariy";
+
+    fn sequencer() -> Sequencer {
+        let (transcript, _) = transcript_with(&[]);
+        let sessions = HashMap::from([
+            ("alice".to_string(), ALICE.to_string()),
+            ("bob".to_string(), BOB.to_string()),
+        ]);
+        let lobby = Lobby::new(LobbyConfig {
+            min_poll_interval: Duration::ZERO,
+            ..Default::default()
+        });
+        Sequencer::new(transcript, sessions, lobby)
+    }
+
+    fn take_turn(sequencer: &mut Sequencer, session_id: &str) -> BatchContribution {
+        match sequencer.try_contribute(session_id).unwrap() {
+            TryContributeResponse::BatchContribution(batch) => batch,
+            TryContributeResponse::InProgress(msg) => panic!("still in the lobby: {msg}"),
+        }
+    }
+
+    #[test]
+    fn contributions_extend_the_transcript() {
+        let mut sequencer = sequencer();
+
+        for (session_id, identity) in [("alice", ALICE), ("bob", BOB)] {
+            let mut batch = take_turn(&mut sequencer, session_id);
+            update_batch(&mut batch, identity, Entropy::new());
+
+            let (index, previous) = sequencer.receive_contribution(session_id).unwrap();
+            verify_contribution(&previous, &batch).unwrap();
+            let entry = sequencer
+                .accept_contribution(session_id, index, batch)
+                .unwrap();
+            let receipt = sequencer.apply(session_id, &entry);
+            assert_eq!(receipt.payload().unwrap().identity, identity);
+        }
+
+        let reports = verify_transcript(sequencer.transcript());
+        assert!(reports.iter().all(SubCeremonyReport::passed));
+        assert!(reports.iter().all(|report| report
+            .signatures
+            .as_ref()
+            .unwrap()
+            .missing
+            .is_empty()));
+        assert_eq!(sequencer.status().num_contributions, 2);
+    }
+
+    #[cfg(feature = "eth")]
+    #[test]
+    fn sign_receipts() {
+        use crate::sequencer::types::ReceiptVerification;
+
+        let wallet = LocalWallet::new(&mut rand_core::OsRng);
+        let address = format!("{:?}", wallet.address());
+        let mut sequencer = sequencer().with_signer(wallet);
+        assert_eq!(sequencer.status().sequencer_address, address);
+
+        let mut batch = take_turn(&mut sequencer, "alice");
+        update_batch(&mut batch, ALICE, Entropy::new());
+        let pubkeys = batch
+            .contributions
+            .iter()
+            .map(|contr| contr.pot_pubkey)
+            .collect::<Vec<_>>();
+
+        let (index, _) = sequencer.receive_contribution("alice").unwrap();
+        let entry = sequencer
+            .accept_contribution("alice", index, batch)
+            .unwrap();
+        let receipt = sequencer.apply("alice", &entry);
+        assert_eq!(
+            receipt.verify(&address, ALICE, &pubkeys),
+            ReceiptVerification::Verified
+        );
+    }
+
+    #[test]
+    fn one_participant_at_a_time() {
+        let mut sequencer = sequencer();
+
+        take_turn(&mut sequencer, "alice");
+        assert!(matches!(
+            sequencer.try_contribute("bob"),
+            Ok(TryContributeResponse::InProgress(_))
+        ));
+        assert_eq!(sequencer.status().lobby_size, 1);
+        assert_eq!(
+            sequencer.receive_contribution("bob").unwrap_err(),
+            ContributionError::Contribute(ContributeError::NotUsersTurn)
+        );
+        assert!(matches!(
+            sequencer.try_contribute("mallory"),
+            Err(TryContributeError::UnknownSessionId)
+        ));
+
+        sequencer.abort_contribution("alice").unwrap();
+        assert_eq!(
+            sequencer.abort_contribution("alice").unwrap_err(),
+            ContributionAbortError::Contribute(ContributeError::NotUsersTurn)
+        );
+        take_turn(&mut sequencer, "bob");
+    }
+
+    #[test]
+    fn ban_participants() {
+        let mut sequencer = sequencer();
+        take_turn(&mut sequencer, "alice");
+        sequencer.try_contribute("bob").unwrap();
+        assert_eq!(sequencer.lobby_state().waiting, vec![BOB.to_string()]);
+
+        sequencer.ban(&ALICE.to_uppercase());
+        let state = sequencer.lobby_state();
+        assert!(state.contributing.is_none());
+        assert_eq!(state.banned, vec![ALICE.to_string()]);
+        assert!(matches!(
+            sequencer.try_contribute("alice"),
+            Err(TryContributeError::UnknownSessionId)
+        ));
+        assert_eq!(
+            sequencer.add_session(ALICE.to_string()),
+            Err(AuthError::Banned)
+        );
+
+        take_turn(&mut sequencer, "bob");
+        assert_eq!(sequencer.force_abort(), Some(BOB.to_string()));
+        assert_eq!(sequencer.force_abort(), None);
+
+        assert!(sequencer.unban(ALICE));
+        assert!(!sequencer.unban(ALICE));
+        sequencer.add_session(ALICE.to_string()).unwrap();
+    }
+
+    #[test]
+    fn reject_contribution_overtaken_during_verification() {
+        let mut sequencer = sequencer();
+        let mut stale = take_turn(&mut sequencer, "alice");
+        update_batch(&mut stale, ALICE, Entropy::new());
+        let (index, _) = sequencer.receive_contribution("alice").unwrap();
+
+        // the operator aborts the turn while it's being verified, and bob contributes meanwhile
+        sequencer.try_contribute("bob").unwrap();
+        sequencer.force_abort();
+        let mut batch = take_turn(&mut sequencer, "bob");
+        update_batch(&mut batch, BOB, Entropy::new());
+        let (bob_index, _) = sequencer.receive_contribution("bob").unwrap();
+        let entry = sequencer
+            .accept_contribution("bob", bob_index, batch)
+            .unwrap();
+        sequencer.apply("bob", &entry);
+
+        take_turn(&mut sequencer, "alice");
+        assert_eq!(
+            sequencer
+                .accept_contribution("alice", index, stale)
+                .unwrap_err(),
+            ContributionError::Contribute(ContributeError::NotUsersTurn)
+        );
+    }
+
+    #[test]
+    fn verify_one_submission_at_a_time() {
+        let mut sequencer = sequencer();
+        take_turn(&mut sequencer, "alice");
+        sequencer.receive_contribution("alice").unwrap();
+
+        // while the first one is being verified
+        assert_eq!(
+            sequencer.receive_contribution("alice").unwrap_err(),
+            ContributionError::Contribute(ContributeError::AlreadySubmitted)
+        );
+
+        sequencer.abort_contribution("alice").unwrap();
+        take_turn(&mut sequencer, "alice");
+        sequencer.receive_contribution("alice").unwrap();
+    }
+
+    #[test]
+    fn keep_the_turn_until_the_contribution_is_applied() {
+        let mut sequencer = sequencer();
+        let mut batch = take_turn(&mut sequencer, "alice");
+        update_batch(&mut batch, ALICE, Entropy::new());
+        let (index, _) = sequencer.receive_contribution("alice").unwrap();
+        let entry = sequencer
+            .accept_contribution("alice", index, batch)
+            .unwrap();
+
+        // while the contribution is being journaled
+        assert!(matches!(
+            sequencer.try_contribute("bob"),
+            Ok(TryContributeResponse::InProgress(_))
+        ));
+
+        sequencer.apply("alice", &entry);
+        let batch = take_turn(&mut sequencer, "bob");
+        assert_eq!(
+            batch.contributions[0].pot_pubkey,
+            entry.contribution.contributions[0].pot_pubkey
+        );
+    }
+
+    #[test]
+    fn reject_contribution_without_entropy() {
+        let mut sequencer = sequencer();
+        let previous = take_turn(&mut sequencer, "alice");
+
+        assert_eq!(
+            verify_contribution(&previous, &previous).unwrap_err(),
+            ContributionError::Ceremony(CeremonyError::ContributionNoEntropy)
+        );
+    }
+}
@@ -0,0 +1,374 @@
+//! Durable storage of a self-hosted ceremony.
+//!
+//! Every accepted contribution is first appended to a journal, and only then added to the
+//! transcript and acknowledged with a receipt. The transcript itself is saved as a checkpoint
+//! that replaces the previous one atomically, after which the journal starts over. Whatever the
+//! moment of a crash, the last checkpoint and the journal hold every acknowledged contribution.
+//!
+//! Snapshots the operator publishes are kept next to the checkpoint, and replaced the same way.
+
+use color_eyre::{eyre::bail, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    ffi::OsString,
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use super::{extend_transcript, verify_contribution};
+use crate::{
+    contribution::types::{BatchContribution, BatchTranscript},
+    transcript::{current_contribution, verify_transcript},
+};
+
+/// A contribution accepted by the sequencer.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct JournalEntry {
+    /// Position of the contribution in the transcript, where 0 is the genesis.
+    pub index: usize,
+    pub identity: String,
+    pub contribution: BatchContribution,
+}
+
+/// The transcript checkpoint at `transcript_path`, and the journal of contributions accepted
+/// since and the published snapshot next to it.
+pub struct Store {
+    transcript_path: PathBuf,
+    journal_path: PathBuf,
+    snapshot_path: PathBuf,
+}
+
+impl Store {
+    pub fn new<P: Into<PathBuf>>(transcript_path: P) -> Self {
+        let transcript_path = transcript_path.into();
+        let journal_path = with_suffix(&transcript_path, ".journal");
+        let snapshot_path = with_suffix(&transcript_path, ".snapshot");
+
+        Self {
+            transcript_path,
+            journal_path,
+            snapshot_path,
+        }
+    }
+
+    /// Loads the checkpoint, replays the journal on top of it and checks the result, which then
+    /// becomes the new checkpoint.
+    pub fn recover(&self) -> Result<BatchTranscript> {
+        let mut transcript: BatchTranscript =
+            serde_json::from_slice(&fs::read(&self.transcript_path)?)?;
+
+        for entry in self.journal()? {
+            let participants = transcript.participant_ids.len();
+            if entry.index < participants {
+                // already part of the checkpoint
+                continue;
+            }
+            if entry.index > participants {
+                bail!(
+                    "The journal skips from contribution #{participants} to #{}.",
+                    entry.index
+                );
+            }
+
+            let previous = current_contribution(&transcript);
+            if let Err(err) = verify_contribution(&previous, &entry.contribution) {
+                bail!(
+                    "Journaled contribution #{} of {} is invalid: {err}",
+                    entry.index,
+                    entry.identity
+                );
+            }
+            extend_transcript(&mut transcript, &entry);
+        }
+
+        if let Some(report) = verify_transcript(&transcript)
+            .iter()
+            .find(|report| !report.passed())
+        {
+            bail!("Transcript #{} is invalid.", report.index);
+        }
+
+        self.checkpoint(&transcript)?;
+        Ok(transcript)
+    }
+
+    /// Records an accepted contribution. It's durable once this returns.
+    pub fn append(&self, entry: &JournalEntry) -> Result<()> {
+        let mut line = serde_json::to_vec(entry)?;
+        line.push(b'\n');
+
+        let journal = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.journal_path)?;
+        write_all_or_nothing(&journal, |mut journal| {
+            journal.write_all(&line)?;
+            journal.sync_all()
+        })?;
+
+        Ok(())
+    }
+
+    /// Replaces the checkpoint with `transcript`, which must include every journaled contribution.
+    pub fn checkpoint(&self, transcript: &BatchTranscript) -> Result<()> {
+        let temp_path = self.write_temp(transcript)?;
+        self.replace(&temp_path)?;
+        self.truncate_journal()
+    }
+
+    /// Replaces the published snapshot with the serialized transcript in `bytes`.
+    pub fn write_snapshot(&self, bytes: &[u8]) -> Result<()> {
+        let temp_path = with_suffix(&self.snapshot_path, ".tmp");
+        write_synced(&temp_path, bytes)?;
+        fs::rename(&temp_path, &self.snapshot_path)?;
+        sync_dir(&self.snapshot_path)?;
+
+        Ok(())
+    }
+
+    /// The published snapshot, if there is one.
+    pub fn snapshot(&self) -> Result<Option<Vec<u8>>> {
+        match fs::read(&self.snapshot_path) {
+            Ok(bytes) => Ok(Some(bytes)),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    fn write_temp(&self, transcript: &BatchTranscript) -> Result<PathBuf> {
+        let temp_path = with_suffix(&self.transcript_path, ".tmp");
+        write_synced(&temp_path, &serde_json::to_vec(transcript)?)?;
+
+        Ok(temp_path)
+    }
+
+    fn replace(&self, temp_path: &Path) -> Result<()> {
+        fs::rename(temp_path, &self.transcript_path)?;
+        sync_dir(&self.transcript_path)?;
+
+        Ok(())
+    }
+
+    fn truncate_journal(&self) -> Result<()> {
+        match OpenOptions::new().write(true).open(&self.journal_path) {
+            Ok(journal) => {
+                journal.set_len(0)?;
+                journal.sync_all()?;
+                Ok(())
+            }
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(()),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Entries of the journal, oldest first. A last line that was cut short by a crash is
+    /// ignored: its contribution was never acknowledged.
+    fn journal(&self) -> Result<Vec<JournalEntry>> {
+        let bytes = match fs::read(&self.journal_path) {
+            Ok(bytes) => bytes,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(err) => return Err(err.into()),
+        };
+
+        let mut lines = bytes.split(|&byte| byte == b'\n').collect::<Vec<_>>();
+        // whatever follows the last newline is either nothing or an unfinished entry
+        lines.pop();
+
+        let mut entries = Vec::new();
+        for line in lines {
+            entries.push(serde_json::from_slice(line)?);
+        }
+
+        Ok(entries)
+    }
+}
+
+fn write_synced(path: &Path, bytes: &[u8]) -> io::Result<()> {
+    let mut file = File::create(path)?;
+    file.write_all(bytes)?;
+    file.sync_all()
+}
+
+/// Runs `write` at the end of `file`, and cuts off whatever it wrote if it fails. A torn line
+/// left in the journal would be followed by the next entry, and recovery only tolerates one at
+/// the very end.
+fn write_all_or_nothing<F: FnOnce(&File) -> io::Result<()>>(
+    file: &File,
+    write: F,
+) -> io::Result<()> {
+    let len = file.metadata()?.len();
+    write(file).inspect_err(|_| {
+        let _ = file.set_len(len);
+    })
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut path = OsString::from(path);
+    path.push(suffix);
+    path.into()
+}
+
+/// Makes a rename in the directory of `path` durable.
+#[cfg(unix)]
+fn sync_dir(path: &Path) -> io::Result<()> {
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    File::open(dir)?.sync_all()
+}
+
+#[cfg(not(unix))]
+fn sync_dir(_path: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        contribution::{entropy::Entropy, update_batch},
+        transcript::tests::transcript_with,
+    };
+
+    const IDENTITY: &str = "eth|0x0000000000000000000000000000000000000001";
+
+    fn temp_store(name: &str) -> (Store, BatchTranscript) {
+        let dir = std::env::temp_dir().join(format!("majlis-kzg-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+
+        let (genesis, _) = transcript_with(&[]);
+        let store = Store::new(dir.join("transcript.json"));
+        store.checkpoint(&genesis).unwrap();
+        (store, genesis)
+    }
+
+    fn next_entry(transcript: &BatchTranscript) -> JournalEntry {
+        let mut contribution = current_contribution(transcript);
+        update_batch(&mut contribution, IDENTITY, Entropy::new());
+
+        JournalEntry {
+            index: transcript.participant_ids.len(),
+            identity: IDENTITY.to_string(),
+            contribution,
+        }
+    }
+
+    /// Accepts a contribution the way the server does, up to the checkpoint.
+    fn contribute(store: &Store, transcript: &mut BatchTranscript) -> JournalEntry {
+        let entry = next_entry(transcript);
+        store.append(&entry).unwrap();
+        extend_transcript(transcript, &entry);
+        entry
+    }
+
+    fn cleanup(store: &Store) {
+        fs::remove_dir_all(store.transcript_path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn crash_before_checkpoint() {
+        let (store, mut transcript) = temp_store("store-journal");
+        contribute(&store, &mut transcript);
+        contribute(&store, &mut transcript);
+
+        let recovered = store.recover().unwrap();
+        assert_eq!(recovered.participant_ids.len(), 3);
+        assert_eq!(
+            recovered.transcripts[0].witness.pot_pubkeys,
+            transcript.transcripts[0].witness.pot_pubkeys
+        );
+
+        // recovery checkpoints, so it's idempotent
+        assert!(store.journal().unwrap().is_empty());
+        assert_eq!(store.recover().unwrap().participant_ids.len(), 3);
+        cleanup(&store);
+    }
+
+    #[test]
+    fn crash_while_appending() {
+        let (store, mut transcript) = temp_store("store-torn");
+        contribute(&store, &mut transcript);
+
+        let mut line = serde_json::to_vec(&next_entry(&transcript)).unwrap();
+        line.truncate(line.len() / 2);
+        let mut journal = OpenOptions::new()
+            .append(true)
+            .open(&store.journal_path)
+            .unwrap();
+        journal.write_all(&line).unwrap();
+
+        assert_eq!(store.recover().unwrap().participant_ids.len(), 2);
+        cleanup(&store);
+    }
+
+    #[test]
+    fn fail_while_appending() {
+        let (store, mut transcript) = temp_store("store-failed");
+        contribute(&store, &mut transcript);
+
+        let line = serde_json::to_vec(&next_entry(&transcript)).unwrap();
+        let journal = OpenOptions::new()
+            .append(true)
+            .open(&store.journal_path)
+            .unwrap();
+        let res = write_all_or_nothing(&journal, |mut journal| {
+            journal.write_all(&line[..line.len() / 2])?;
+            Err(io::Error::other("no space left on device"))
+        });
+        assert!(res.is_err());
+
+        // the next entry starts on a line of its own
+        contribute(&store, &mut transcript);
+        assert_eq!(store.recover().unwrap().participant_ids.len(), 3);
+        cleanup(&store);
+    }
+
+    #[test]
+    fn crash_while_writing_checkpoint() {
+        let (store, mut transcript) = temp_store("store-temp");
+        contribute(&store, &mut transcript);
+
+        fs::write(
+            with_suffix(&store.transcript_path, ".tmp"),
+            b"{\"transcripts\":",
+        )
+        .unwrap();
+
+        assert_eq!(store.recover().unwrap().participant_ids.len(), 2);
+        cleanup(&store);
+    }
+
+    #[test]
+    fn crash_before_truncating_journal() {
+        let (store, mut transcript) = temp_store("store-replaced");
+        contribute(&store, &mut transcript);
+
+        let temp_path = store.write_temp(&transcript).unwrap();
+        store.replace(&temp_path).unwrap();
+        contribute(&store, &mut transcript);
+
+        let recovered = store.recover().unwrap();
+        assert_eq!(recovered.participant_ids.len(), 3);
+        cleanup(&store);
+    }
+
+    #[test]
+    fn reject_invalid_journal() {
+        let (store, mut transcript) = temp_store("store-invalid");
+        let mut entry = contribute(&store, &mut transcript);
+
+        // the same contribution again doesn't build on the previous one
+        entry.index += 1;
+        store.append(&entry).unwrap();
+        assert!(store.recover().is_err());
+
+        // a contribution is missing
+        store.truncate_journal().unwrap();
+        store.append(&entry).unwrap();
+        assert!(store.recover().is_err());
+        cleanup(&store);
+    }
+}
@@ -0,0 +1,135 @@
+use ethers::core::types::{Address, Signature};
+use rayon::prelude::*;
+
+use crate::contribution::{
+    signing::construct_pubkeys_eip712_typed_data,
+    types::{BatchTranscript, Transcript},
+};
+
+/// Ethereum participants of a transcript who signed their pubkeys with their account.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct EcdsaReport {
+    /// Participants whose signature recovers to their own address.
+    pub verified: Vec<String>,
+    /// Participants whose signature is malformed or recovers to another address.
+    pub invalid: Vec<String>,
+    /// Ethereum participants who didn't sign. Signatures are optional, so these don't make the
+    /// transcript invalid.
+    pub missing: Vec<String>,
+}
+
+/// Recovers the signer of every ECDSA signature in the transcript, over the EIP-712 typed data of
+/// the participant's pubkeys, and compares it with the `eth|0x…` participant ID. Participants
+/// who can't sign, like GitHub users, are skipped unless they did.
+pub fn verify_ecdsa_signatures(transcript: &BatchTranscript) -> EcdsaReport {
+    let signatures = &transcript.participant_ecds_signatures;
+
+    let outcomes = (0..transcript.participant_ids.len())
+        .into_par_iter()
+        .filter_map(|i| {
+            let participant = &transcript.participant_ids[i];
+            let outcome = match signatures.get(i).filter(|signature| !signature.is_empty()) {
+                Some(signature) => Some(verify_signature(
+                    &transcript.transcripts,
+                    i,
+                    participant,
+                    signature,
+                )),
+                None if participant.starts_with("eth|") => None,
+                None => return None,
+            };
+            Some((participant.clone(), outcome))
+        })
+        .collect::<Vec<_>>();
+
+    let mut report = EcdsaReport::default();
+    for (participant, outcome) in outcomes {
+        match outcome {
+            Some(true) => report.verified.push(participant),
+            Some(false) => report.invalid.push(participant),
+            None => report.missing.push(participant),
+        }
+    }
+
+    report
+}
+
+/// Whether the signature of the `index`-th participant was made by its Ethereum account.
+fn verify_signature(
+    transcripts: &[Transcript],
+    index: usize,
+    participant: &str,
+    signature: &str,
+) -> bool {
+    let Some(address) = participant
+        .strip_prefix("eth|")
+        .and_then(|address| address.parse::<Address>().ok())
+    else {
+        return false;
+    };
+
+    let Ok(signature) = signature.parse::<Signature>() else {
+        return false;
+    };
+
+    let pubkeys = transcripts.iter().map(|transcript| {
+        transcript
+            .witness
+            .pot_pubkeys
+            .get(index)
+            .map(|pubkey| (transcript.num_g1_powers, transcript.num_g2_powers, pubkey))
+    });
+    let Some(Ok(typed_data)) = pubkeys
+        .collect::<Option<Vec<_>>>()
+        .map(construct_pubkeys_eip712_typed_data)
+    else {
+        return false;
+    };
+
+    signature.recover_typed_data(typed_data).ok() == Some(address)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        contribution::signing::construct_contribution_eip712_typed_data,
+        transcript::tests::transcript_with,
+    };
+    use ethers::{
+        core::types::{transaction::eip712::Eip712, H256},
+        signers::{LocalWallet, Signer},
+    };
+
+    const KEY: &str = "4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318";
+
+    #[test]
+    fn flag_signatures_from_other_accounts() {
+        let wallet = KEY.parse::<LocalWallet>().unwrap();
+        let participant = format!("eth|{:?}", wallet.address());
+        // eth|0x02 didn't sign
+        let (mut transcript, batch) = transcript_with(&[
+            "git|26515232|@kariy",
+            "eth|0x02",
+            "eth|0x01",
+            participant.as_str(),
+        ]);
+
+        let typed_data = construct_contribution_eip712_typed_data(&batch).unwrap();
+        let hash = H256::from(typed_data.encode_eip712().unwrap());
+        let signature = format!("0x{}", wallet.sign_hash(hash));
+
+        // the same signature, claimed by someone else
+        transcript.participant_ecds_signatures[3] = signature.clone();
+        transcript.participant_ecds_signatures[4] = signature;
+
+        assert_eq!(
+            verify_ecdsa_signatures(&transcript),
+            EcdsaReport {
+                verified: vec![participant],
+                invalid: vec!["eth|0x01".to_string()],
+                missing: vec!["eth|0x02".to_string()],
+            }
+        );
+    }
+}
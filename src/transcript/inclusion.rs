@@ -0,0 +1,146 @@
+use crate::contribution::{
+    point::G2Point,
+    types::{BatchTranscript, Transcript},
+    verification::verify_pubkey,
+};
+
+/// How our contribution at `index` fits in each sub-ceremony of a transcript.
+#[derive(Debug)]
+pub struct InclusionReport {
+    pub index: usize,
+    pub ceremonies: Vec<SubCeremonyInclusion>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct SubCeremonyInclusion {
+    /// The pubkey at our index is the one we contributed with.
+    pub pubkey_matches: bool,
+    /// Our running product is the previous one raised to our secret.
+    pub linked_to_previous: bool,
+    /// The next running product is ours raised to the next secret, or ours is the first power of
+    /// tau if we were the last participant.
+    pub linked_to_next: bool,
+}
+
+impl SubCeremonyInclusion {
+    pub fn proven(&self) -> bool {
+        self.pubkey_matches && self.linked_to_previous && self.linked_to_next
+    }
+}
+
+impl InclusionReport {
+    /// Whether our secret is part of every sub-ceremony, and thus of the final SRS.
+    pub fn proven(&self) -> bool {
+        !self.ceremonies.is_empty() && self.ceremonies.iter().all(SubCeremonyInclusion::proven)
+    }
+}
+
+/// Index of `identity` among the participants. Identities are compared case-insensitively, as
+/// Ethereum addresses and GitHub handles are.
+pub fn find_participant(transcript: &BatchTranscript, identity: &str) -> Option<usize> {
+    transcript
+        .participant_ids
+        .iter()
+        .position(|id| id.eq_ignore_ascii_case(identity))
+}
+
+/// Checks that the participant at `index` contributed with `pubkeys`, one per sub-ceremony, and
+/// that its running products are part of the chain that leads to the final powers.
+pub fn prove_inclusion(
+    transcript: &BatchTranscript,
+    index: usize,
+    pubkeys: &[G2Point],
+) -> InclusionReport {
+    let ceremonies = transcript
+        .transcripts
+        .iter()
+        .enumerate()
+        .map(|(i, sub)| check_sub_ceremony(sub, index, pubkeys.get(i)))
+        .collect();
+
+    InclusionReport { index, ceremonies }
+}
+
+fn check_sub_ceremony(
+    transcript: &Transcript,
+    index: usize,
+    pubkey: Option<&G2Point>,
+) -> SubCeremonyInclusion {
+    let products = &transcript.witness.running_products;
+    let pubkeys = &transcript.witness.pot_pubkeys;
+
+    let ours = match (products.get(index), pubkeys.get(index)) {
+        (Some(product), Some(pubkey)) if index > 0 => (product, pubkey),
+        _ => {
+            return SubCeremonyInclusion {
+                pubkey_matches: false,
+                linked_to_previous: false,
+                linked_to_next: false,
+            }
+        }
+    };
+
+    let linked_to_next = match (products.get(index + 1), pubkeys.get(index + 1)) {
+        (Some(next), Some(next_pubkey)) => verify_pubkey(ours.0, next, next_pubkey),
+        _ => transcript.powers_of_tau.g1_powers.get(1) == Some(ours.0),
+    };
+
+    SubCeremonyInclusion {
+        pubkey_matches: pubkey == Some(ours.1),
+        linked_to_previous: verify_pubkey(&products[index - 1], ours.0, ours.1),
+        linked_to_next,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transcript::tests::transcript_with;
+
+    fn our_pubkeys(transcript: &BatchTranscript, index: usize) -> Vec<G2Point> {
+        transcript
+            .transcripts
+            .iter()
+            .map(|sub| sub.witness.pot_pubkeys[index])
+            .collect()
+    }
+
+    #[test]
+    fn prove_inclusion_anywhere_in_the_chain() {
+        let (transcript, _) = transcript_with(&["eth|0x01", "git|26515232|@kariy", "eth|0x03"]);
+
+        assert_eq!(
+            find_participant(&transcript, "git|26515232|@Kariy"),
+            Some(2)
+        );
+        assert_eq!(find_participant(&transcript, "eth|0x04"), None);
+
+        for index in 1..=3 {
+            let report = prove_inclusion(&transcript, index, &our_pubkeys(&transcript, index));
+            assert!(report.proven(), "participant #{index}");
+        }
+    }
+
+    #[test]
+    fn reject_foreign_pubkeys_and_broken_links() {
+        let (mut transcript, _) = transcript_with(&["eth|0x01", "eth|0x02", "eth|0x03"]);
+
+        let report = prove_inclusion(&transcript, 2, &our_pubkeys(&transcript, 1));
+        assert!(report.ceremonies.iter().all(|c| !c.pubkey_matches));
+
+        let pubkeys = our_pubkeys(&transcript, 2);
+        transcript.transcripts[1].witness.running_products[3] =
+            transcript.transcripts[1].witness.running_products[1];
+
+        let report = prove_inclusion(&transcript, 2, &pubkeys);
+        assert!(report.ceremonies[0].proven());
+        assert_eq!(
+            report.ceremonies[1],
+            SubCeremonyInclusion {
+                pubkey_matches: true,
+                linked_to_previous: true,
+                linked_to_next: false,
+            }
+        );
+    }
+}
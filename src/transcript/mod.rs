@@ -0,0 +1,628 @@
+use bls12_381::{multi_miller_loop, G1Affine, G1Projective, G2Affine, G2Prepared, Gt, Scalar};
+use rand_chacha::ChaCha20Rng;
+use rand_core::{RngCore, SeedableRng};
+use rayon::prelude::*;
+use std::fmt;
+
+#[cfg(feature = "eth")]
+pub mod ecdsa;
+pub mod inclusion;
+pub mod signatures;
+
+use crate::{
+    contribution::{
+        point::{G1Point, G2Point},
+        subgroup::batch_subgroup_check,
+        types::{
+            BatchContribution, BatchTranscript, Contribution, PowersOfTau, Transcript,
+            TranscriptPowersOfTau, Witness,
+        },
+        verification::{verify_powers, verify_pubkey},
+    },
+    sequencer::types::CeremonyError,
+};
+use signatures::{verify_signatures, SignatureReport};
+
+/// Running products are checked against their pubkeys in chunks of this size in parallel.
+const CHUNK_SIZE: usize = 256;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Check {
+    WitnessLengths,
+    NumPowers,
+    NonZero,
+    FirstValues,
+    Subgroups,
+    PowersOfTau,
+    RunningProducts,
+}
+
+impl fmt::Display for Check {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::WitnessLengths => write!(f, "Witness lengths"),
+            Self::NumPowers => write!(f, "Number of powers"),
+            Self::NonZero => write!(f, "Non-zero points"),
+            Self::FirstValues => write!(f, "Generators as first powers"),
+            Self::Subgroups => write!(f, "Subgroup membership"),
+            Self::PowersOfTau => write!(f, "Powers of tau"),
+            Self::RunningProducts => write!(f, "Running products"),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct CheckFailure {
+    pub code: CeremonyError,
+    pub detail: String,
+}
+
+impl fmt::Display for CheckFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({})", self.detail, self.code)
+    }
+}
+
+type CheckResult = Result<(), CheckFailure>;
+
+fn fail<T: Into<String>>(code: CeremonyError, detail: T) -> CheckResult {
+    Err(CheckFailure {
+        code,
+        detail: detail.into(),
+    })
+}
+
+/// Outcome of every check of a single sub-ceremony.
+#[derive(Debug)]
+pub struct SubCeremonyReport {
+    pub index: usize,
+    pub num_g1_powers: i32,
+    pub num_g2_powers: i32,
+    pub num_participants: usize,
+    pub checks: Vec<(Check, CheckResult)>,
+    /// `None` if the witness is malformed and the signatures couldn't be matched to participants.
+    pub signatures: Option<SignatureReport>,
+}
+
+impl SubCeremonyReport {
+    /// Whether every check passed and no participant has an invalid signature. Missing
+    /// signatures are allowed.
+    pub fn passed(&self) -> bool {
+        self.checks.iter().all(|(_, result)| result.is_ok())
+            && self
+                .signatures
+                .as_ref()
+                .is_none_or(|signatures| signatures.invalid.is_empty())
+    }
+}
+
+/// The transcript a ceremony starts from, with a sub-ceremony of `(num_g1_powers, num_g2_powers)`
+/// for every size. Every power is the generator, as if the secret were 1, and the witness holds
+/// that of the empty participant at index 0. Negative sizes get no powers at all.
+pub fn genesis(sizes: &[(i32, i32)]) -> BatchTranscript {
+    let g1 = G1Point::generator();
+    let g2 = G2Point::generator();
+
+    let transcripts = sizes
+        .iter()
+        .map(|&(num_g1, num_g2)| Transcript {
+            num_g1_powers: num_g1,
+            num_g2_powers: num_g2,
+            powers_of_tau: TranscriptPowersOfTau {
+                g1_powers: vec![g1; usize::try_from(num_g1).unwrap_or_default()],
+                g2_powers: vec![g2; usize::try_from(num_g2).unwrap_or_default()],
+            },
+            witness: Witness {
+                running_products: vec![g1],
+                pot_pubkeys: vec![g2],
+                bls_signatures: vec![String::new()],
+            },
+        })
+        .collect();
+
+    BatchTranscript {
+        transcripts,
+        participant_ids: vec![String::new()],
+        participant_ecds_signatures: vec![String::new()],
+    }
+}
+
+/// The latest powers of every sub-ceremony, as a batch to build on.
+pub fn current_contribution(transcript: &BatchTranscript) -> BatchContribution {
+    let contributions = transcript
+        .transcripts
+        .iter()
+        .map(|transcript| Contribution {
+            num_g1_powers: transcript.num_g1_powers,
+            num_g2_powers: transcript.num_g2_powers,
+            powers_of_tau: PowersOfTau {
+                g1_powers: transcript.powers_of_tau.g1_powers.clone(),
+                g2_powers: transcript.powers_of_tau.g2_powers.clone(),
+            },
+            pot_pubkey: transcript
+                .witness
+                .pot_pubkeys
+                .last()
+                .copied()
+                .unwrap_or_else(G2Point::generator),
+            bls_signature: None,
+        })
+        .collect();
+
+    BatchContribution {
+        contributions,
+        ecdsa_signature: None,
+    }
+}
+
+/// Verifies every sub-ceremony of a transcript. Checks that depend on a failed one (eg. pairings
+/// on arrays of the wrong length) are skipped.
+pub fn verify_transcript(transcript: &BatchTranscript) -> Vec<SubCeremonyReport> {
+    transcript
+        .transcripts
+        .iter()
+        .enumerate()
+        .map(|(index, sub)| verify_sub_ceremony(index, sub, &transcript.participant_ids))
+        .collect()
+}
+
+fn verify_sub_ceremony(
+    index: usize,
+    transcript: &Transcript,
+    participant_ids: &[String],
+) -> SubCeremonyReport {
+    let mut report = SubCeremonyReport {
+        index,
+        num_g1_powers: transcript.num_g1_powers,
+        num_g2_powers: transcript.num_g2_powers,
+        // the first entry of the witness is the generator the ceremony started from
+        num_participants: transcript.witness.running_products.len().saturating_sub(1),
+        checks: Vec::new(),
+        signatures: None,
+    };
+
+    let structure = [
+        (
+            Check::WitnessLengths,
+            check_witness_lengths(transcript, participant_ids),
+        ),
+        (Check::NumPowers, check_num_powers(transcript)),
+    ];
+    let well_formed = structure.iter().all(|(_, result)| result.is_ok());
+    report.checks.extend(structure);
+
+    if !well_formed {
+        return report;
+    }
+
+    report.checks.extend([
+        (Check::NonZero, check_non_zero(transcript)),
+        (Check::FirstValues, check_first_values(transcript)),
+        (Check::Subgroups, check_subgroups(transcript)),
+    ]);
+
+    // pairings are only meaningful on points from the subgroup
+    if report.passed() {
+        report.checks.extend([
+            (Check::PowersOfTau, check_powers_of_tau(transcript)),
+            (Check::RunningProducts, check_running_products(transcript)),
+        ]);
+    }
+
+    report.signatures = Some(verify_signatures(
+        participant_ids,
+        &transcript.witness.pot_pubkeys,
+        &transcript.witness.bls_signatures,
+    ));
+
+    report
+}
+
+fn check_witness_lengths(transcript: &Transcript, participant_ids: &[String]) -> CheckResult {
+    let witness = &transcript.witness;
+    let (products, pubkeys, signatures, participants) = (
+        witness.running_products.len(),
+        witness.pot_pubkeys.len(),
+        witness.bls_signatures.len(),
+        participant_ids.len(),
+    );
+
+    if products == 0 || products != pubkeys || products != signatures || products != participants {
+        return fail(
+            CeremonyError::WitnessLengthMismatch,
+            format!(
+                "{products} running products, {pubkeys} pubkeys, {signatures} signatures and \
+                 {participants} participant IDs"
+            ),
+        );
+    }
+
+    Ok(())
+}
+
+fn check_num_powers(transcript: &Transcript) -> CheckResult {
+    let powers = &transcript.powers_of_tau;
+
+    if transcript.num_g1_powers < 2 || powers.g1_powers.len() != transcript.num_g1_powers as usize {
+        return fail(
+            CeremonyError::UnexpectedNumG1Powers,
+            format!(
+                "expected {} G1 powers, got {}",
+                transcript.num_g1_powers,
+                powers.g1_powers.len()
+            ),
+        );
+    }
+
+    if transcript.num_g2_powers < 2 || powers.g2_powers.len() != transcript.num_g2_powers as usize {
+        return fail(
+            CeremonyError::UnexpectedNumG2Powers,
+            format!(
+                "expected {} G2 powers, got {}",
+                transcript.num_g2_powers,
+                powers.g2_powers.len()
+            ),
+        );
+    }
+
+    if transcript.num_g2_powers > transcript.num_g1_powers {
+        return fail(
+            CeremonyError::UnsupportedMoreG2Powers,
+            "more G2 powers than G1 powers",
+        );
+    }
+
+    Ok(())
+}
+
+fn check_non_zero(transcript: &Transcript) -> CheckResult {
+    let powers = &transcript.powers_of_tau;
+    let witness = &transcript.witness;
+
+    if let Some(i) = powers.g1_powers.iter().position(is_zero_g1) {
+        return fail(CeremonyError::ZeroG1, format!("G1 power #{i} is zero"));
+    }
+    if let Some(i) = powers.g2_powers.iter().position(is_zero_g2) {
+        return fail(CeremonyError::ZeroG2, format!("G2 power #{i} is zero"));
+    }
+    if let Some(i) = witness.running_products.iter().position(is_zero_g1) {
+        return fail(
+            CeremonyError::InvalidWitnessProduct,
+            format!("running product #{i} is zero"),
+        );
+    }
+    if let Some(i) = witness.pot_pubkeys.iter().position(is_zero_g2) {
+        return fail(CeremonyError::ZeroPubkey, format!("pubkey #{i} is zero"));
+    }
+
+    Ok(())
+}
+
+fn check_first_values(transcript: &Transcript) -> CheckResult {
+    let powers = &transcript.powers_of_tau;
+    let witness = &transcript.witness;
+
+    if powers.g1_powers[0] != G1Point::generator() {
+        return fail(
+            CeremonyError::InvalidG1FirstValue,
+            "G1 power #0 is not the generator",
+        );
+    }
+    if powers.g2_powers[0] != G2Point::generator() {
+        return fail(
+            CeremonyError::InvalidG2FirstValue,
+            "G2 power #0 is not the generator",
+        );
+    }
+    if witness.running_products[0] != G1Point::generator() {
+        return fail(
+            CeremonyError::InvalidWitnessProduct,
+            "running product #0 is not the generator",
+        );
+    }
+    if witness.pot_pubkeys[0] != G2Point::generator() {
+        return fail(
+            CeremonyError::InvalidWitnessPubKey,
+            "pubkey #0 is not the generator",
+        );
+    }
+
+    Ok(())
+}
+
+fn check_subgroups(transcript: &Transcript) -> CheckResult {
+    let powers = &transcript.powers_of_tau;
+    let witness = &transcript.witness;
+
+    if let Err(i) = batch_subgroup_check(&powers.g1_powers) {
+        return fail(
+            CeremonyError::InvalidG1Power,
+            not_in_subgroup("G1 power", i),
+        );
+    }
+    if let Err(i) = batch_subgroup_check(&powers.g2_powers) {
+        return fail(
+            CeremonyError::InvalidG2Power,
+            not_in_subgroup("G2 power", i),
+        );
+    }
+    if let Err(i) = batch_subgroup_check(&witness.running_products) {
+        return fail(
+            CeremonyError::InvalidWitnessProduct,
+            not_in_subgroup("running product", i),
+        );
+    }
+    if let Err(i) = batch_subgroup_check(&witness.pot_pubkeys) {
+        return fail(
+            CeremonyError::InvalidWitnessPubKey,
+            not_in_subgroup("pubkey", i),
+        );
+    }
+
+    Ok(())
+}
+
+fn check_powers_of_tau(transcript: &Transcript) -> CheckResult {
+    let powers = &transcript.powers_of_tau;
+
+    verify_powers(&powers.g1_powers, &powers.g2_powers).or_else(|code| {
+        let detail = match code {
+            CeremonyError::G1PairingFailed => "G1 powers are not successive powers of tau",
+            _ => "G2 powers are not successive powers of tau",
+        };
+        fail(code, detail)
+    })
+}
+
+/// Checks that every running product is the previous one multiplied by the secret of the
+/// matching pubkey, and that the last one is the first power of tau in G1.
+fn check_running_products(transcript: &Transcript) -> CheckResult {
+    let products = &transcript.witness.running_products;
+    let pubkeys = &transcript.witness.pot_pubkeys;
+
+    if products.last() != transcript.powers_of_tau.g1_powers.get(1) {
+        return fail(
+            CeremonyError::InvalidWitnessProduct,
+            "the last running product is not the first power of tau",
+        );
+    }
+
+    if !running_products_chain(products, pubkeys) {
+        let broken = (1..products.len())
+            .into_par_iter()
+            .find_first(|&i| !verify_pubkey(&products[i - 1], &products[i], &pubkeys[i]));
+
+        return fail(
+            CeremonyError::InvalidWitnessProduct,
+            match broken {
+                Some(i) => format!("running product #{i} doesn't match pubkey #{i}"),
+                None => "the running products don't match the pubkeys".to_string(),
+            },
+        );
+    }
+
+    Ok(())
+}
+
+/// Checks `e(products[i], G2) == e(products[i - 1], pubkeys[i])` for every `i` at once, on a random
+/// linear combination of the equations.
+fn running_products_chain(products: &[G1Point], pubkeys: &[G2Point]) -> bool {
+    if products.len() < 2 {
+        return true;
+    }
+
+    let mut rng = ChaCha20Rng::from_entropy();
+    let coefficients = (1..products.len())
+        .map(|_| Scalar::from(rng.next_u64()))
+        .collect::<Vec<_>>();
+
+    let lhs = products[1..]
+        .iter()
+        .zip(&coefficients)
+        .fold(G1Projective::identity(), |acc, (p, r)| acc + p.0 * r);
+
+    // `Σ e(r_i * products[i - 1], pubkeys[i])`, with the Miller loops computed in parallel
+    let indices = (1..products.len()).collect::<Vec<_>>();
+    let rhs = indices
+        .par_chunks(CHUNK_SIZE)
+        .map(|chunk| {
+            let terms = chunk
+                .iter()
+                .map(|&i| {
+                    let scaled = G1Affine::from(products[i - 1].0 * coefficients[i - 1]);
+                    (-scaled, G2Prepared::from(pubkeys[i].0))
+                })
+                .collect::<Vec<_>>();
+
+            multi_miller_loop(&terms.iter().map(|(a, b)| (a, b)).collect::<Vec<_>>())
+        })
+        .reduce(Default::default, |a, b| a + b);
+
+    let lhs = multi_miller_loop(&[(
+        &G1Affine::from(lhs),
+        &G2Prepared::from(G2Affine::generator()),
+    )]);
+
+    (lhs + rhs).final_exponentiation() == Gt::identity()
+}
+
+fn is_zero_g1(point: &G1Point) -> bool {
+    point.0.is_identity().into()
+}
+
+fn is_zero_g2(point: &G2Point) -> bool {
+    point.0.is_identity().into()
+}
+
+fn not_in_subgroup(what: &str, index: usize) -> String {
+    format!("{what} #{index} is not in the prime-order subgroup")
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::contribution::{
+        entropy::Entropy, tests::genesis_batch, update_batch, validation::validate_contribution,
+    };
+
+    /// A transcript of `participants` contributions on top of a genesis batch, along with the
+    /// last contribution.
+    pub(crate) fn transcript_with(participants: &[&str]) -> (BatchTranscript, BatchContribution) {
+        let mut batch = genesis_batch(&[(8, 3), (16, 3)]);
+        let mut witnesses = batch
+            .contributions
+            .iter()
+            .map(|contr| Witness {
+                running_products: vec![G1Point::generator()],
+                pot_pubkeys: vec![contr.pot_pubkey],
+                bls_signatures: vec![String::new()],
+            })
+            .collect::<Vec<_>>();
+
+        for identity in participants {
+            update_batch(&mut batch, identity, Entropy::new());
+
+            for (witness, contr) in witnesses.iter_mut().zip(&batch.contributions) {
+                witness
+                    .running_products
+                    .push(contr.powers_of_tau.g1_powers[1]);
+                witness.pot_pubkeys.push(contr.pot_pubkey);
+                witness
+                    .bls_signatures
+                    .push(contr.bls_signature.clone().unwrap_or_default());
+            }
+        }
+
+        let transcripts = batch
+            .contributions
+            .iter()
+            .zip(witnesses)
+            .map(|(contr, witness)| Transcript {
+                num_g1_powers: contr.num_g1_powers,
+                num_g2_powers: contr.num_g2_powers,
+                powers_of_tau: TranscriptPowersOfTau {
+                    g1_powers: contr.powers_of_tau.g1_powers.clone(),
+                    g2_powers: contr.powers_of_tau.g2_powers.clone(),
+                },
+                witness,
+            })
+            .collect();
+
+        let mut participant_ids = vec![String::new()];
+        participant_ids.extend(participants.iter().map(ToString::to_string));
+
+        let transcript = BatchTranscript {
+            transcripts,
+            participant_ecds_signatures: vec![String::new(); participant_ids.len()],
+            participant_ids,
+        };
+
+        (transcript, batch)
+    }
+
+    fn failures(reports: &[SubCeremonyReport]) -> Vec<(usize, Check, String)> {
+        reports
+            .iter()
+            .flat_map(|report| {
+                report.checks.iter().filter_map(|(check, result)| {
+                    result
+                        .as_ref()
+                        .err()
+                        .map(|failure| (report.index, *check, failure.code.to_string()))
+                })
+            })
+            .collect()
+    }
+
+    #[test]
+    fn accept_valid_transcript() {
+        let (transcript, _) = transcript_with(&["eth|0x01", "eth|0x02", "eth|0x03"]);
+        let reports = verify_transcript(&transcript);
+
+        assert_eq!(reports.len(), 2);
+        assert!(reports.iter().all(SubCeremonyReport::passed));
+        assert_eq!(reports[0].num_participants, 3);
+        assert_eq!(reports[0].signatures, Some(SignatureReport::default()));
+    }
+
+    #[test]
+    fn build_on_genesis() {
+        let transcript = genesis(&[(8, 3), (16, 3)]);
+        let reports = verify_transcript(&transcript);
+        assert!(reports.iter().all(SubCeremonyReport::passed));
+        assert_eq!(reports[1].num_participants, 0);
+
+        let previous = current_contribution(&transcript);
+        let mut batch = previous.clone();
+        update_batch(&mut batch, "eth|0x01", Entropy::new());
+        assert!(validate_contribution(&previous, &batch).is_ok());
+    }
+
+    #[test]
+    fn report_missing_and_invalid_signatures() {
+        let (mut transcript, _) = transcript_with(&["eth|0x01", "git|26515232|@kariy", "eth|0x03"]);
+        let signatures = &mut transcript.transcripts[0].witness.bls_signatures;
+        signatures[1] = String::new();
+        signatures[3] = signatures[2].clone();
+
+        let reports = verify_transcript(&transcript);
+        assert_eq!(
+            reports[0].signatures,
+            Some(SignatureReport {
+                missing: vec!["eth|0x01".to_string()],
+                invalid: vec!["eth|0x03".to_string()],
+            })
+        );
+        assert!(!reports[0].passed());
+        assert!(reports[1].passed());
+    }
+
+    #[test]
+    fn reject_broken_running_products() {
+        let (mut transcript, _) = transcript_with(&["eth|0x01", "eth|0x02", "eth|0x03"]);
+        transcript.transcripts[1].witness.pot_pubkeys.swap(1, 2);
+
+        assert_eq!(
+            failures(&verify_transcript(&transcript)),
+            vec![(
+                1,
+                Check::RunningProducts,
+                "CeremonyError::InvalidWitnessProduct".to_string()
+            )]
+        );
+    }
+
+    #[test]
+    fn reject_mismatched_witness_lengths() {
+        let (mut transcript, _) = transcript_with(&["eth|0x01"]);
+        transcript.transcripts[0].witness.bls_signatures.pop();
+
+        assert_eq!(
+            failures(&verify_transcript(&transcript)),
+            vec![(
+                0,
+                Check::WitnessLengths,
+                "CeremonyError::WitnessLengthMismatch".to_string()
+            )]
+        );
+    }
+
+    #[test]
+    fn reject_zero_and_tampered_powers() {
+        let (mut transcript, _) = transcript_with(&["eth|0x01"]);
+        transcript.transcripts[0].powers_of_tau.g2_powers[2] = G2Point::identity();
+        transcript.transcripts[1].powers_of_tau.g1_powers.swap(5, 6);
+
+        assert_eq!(
+            failures(&verify_transcript(&transcript)),
+            vec![
+                (0, Check::NonZero, "CeremonyError::ZeroG2".to_string()),
+                (
+                    1,
+                    Check::PowersOfTau,
+                    "CeremonyError::G1PairingFailed".to_string()
+                )
+            ]
+        );
+    }
+}
@@ -0,0 +1,75 @@
+use blsful::{PublicKey, Signature};
+use rayon::prelude::*;
+
+use crate::contribution::{
+    identity::{eth_address_to_identity, github_id_to_identity},
+    point::G2Point,
+    utils::bytes_from_hex_str,
+};
+
+/// Participants of a sub-ceremony whose BLS signature couldn't be checked.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct SignatureReport {
+    /// Participants who didn't sign their identity. Signatures are optional, so these don't
+    /// make the transcript invalid.
+    pub missing: Vec<String>,
+    /// Participants whose signature doesn't verify under their pubkey.
+    pub invalid: Vec<String>,
+}
+
+/// Checks the BLS signature of every participant over its identity, under the pubkey it
+/// contributed with. The first entry of each list belongs to the genesis and is skipped.
+pub fn verify_signatures(
+    participant_ids: &[String],
+    pubkeys: &[G2Point],
+    signatures: &[String],
+) -> SignatureReport {
+    let (missing, signed): (Vec<usize>, Vec<usize>) =
+        (1..participant_ids.len()).partition(|&i| signatures[i].is_empty());
+
+    let invalid = signed
+        .into_par_iter()
+        .filter(|&i| !verify_signature(&participant_ids[i], &pubkeys[i], &signatures[i]))
+        .map(|i| participant_ids[i].clone())
+        .collect();
+
+    SignatureReport {
+        missing: missing
+            .into_iter()
+            .map(|i| participant_ids[i].clone())
+            .collect(),
+        invalid,
+    }
+}
+
+/// Whether `signature` is a valid BLS signature of the identity of `participant_id`.
+pub fn verify_signature(participant_id: &str, pubkey: &G2Point, signature: &str) -> bool {
+    let Some(identity) = participant_identity(participant_id) else {
+        return false;
+    };
+
+    let Some(signature) = bytes_from_hex_str(signature)
+        .ok()
+        .and_then(|bytes| <[u8; 48]>::try_from(bytes).ok())
+        .and_then(|bytes| Option::<Signature>::from(Signature::from_bytes(&bytes)))
+    else {
+        return false;
+    };
+
+    let Some(pubkey) = Option::<PublicKey>::from(PublicKey::from_bytes(&pubkey.0.to_compressed()))
+    else {
+        return false;
+    };
+
+    signature.verify(pubkey, identity).into()
+}
+
+/// Rebuilds the identity a participant signed from its ID in the transcript, either
+/// `eth|<address>` or `git|<id>|<@handle>`.
+fn participant_identity(participant_id: &str) -> Option<String> {
+    match participant_id.split('|').collect::<Vec<_>>()[..] {
+        ["eth", address] => eth_address_to_identity(address).ok(),
+        ["git", id, handle] => github_id_to_identity(id.parse().ok()?, handle).ok(),
+        _ => None,
+    }
+}